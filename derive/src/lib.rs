@@ -1,16 +1,32 @@
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
 
-use heck::ToShoutySnekCase;
+use heck::{ToShoutySnekCase, ToSnakeCase, ToUpperCamelCase};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
-use syn::{ItemMod, parse_macro_input};
+use syn::{Ident, ItemMod, parse_macro_input};
 
+use aseprite_loader::binary::chunk::Chunk;
+use aseprite_loader::binary::chunks::tags::AnimationDirection;
+use aseprite_loader::binary::file::parse_file;
+use aseprite_loader::binary::raw_file::{RawFile, parse_raw_file};
 use darling::{FromMeta, ast::NestedMeta};
 
 #[derive(Debug, FromMeta)]
 struct MacroArgs {
     /// #[aseprite(file = "...")]
-    file: String,
+    #[darling(default)]
+    file: Option<String>,
+    /// #[aseprite(dir = "...")], generates a module per file of the directory
+    #[darling(default)]
+    dir: Option<String>,
+    /// #[aseprite(asset_root = "...")], relative to the crate's manifest, "assets" by default
+    #[darling(default)]
+    asset_root: Option<String>,
+    /// #[aseprite(enums)], generates `Tag` and `Slice` enums
+    #[darling(default)]
+    enums: bool,
 }
 
 #[proc_macro_attribute]
@@ -35,46 +51,417 @@ pub fn aseprite(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    let file = args.file;
-    let file_path = format!("assets/{file}");
-    let ase_bytes = match fs::read(&file_path).map_err(|e| {
-        darling::Error::custom(format!("I/O error for file {file_path}: {e}")).with_span(&span)
-    }) {
-        Ok(bytes) => bytes,
-        Err(e) => return e.write_errors().into(),
-    };
-    let raw = match aseprite_loader::loader::AsepriteFile::load(&ase_bytes).map_err(|e| {
-        darling::Error::custom(format!("Loading error for file {file_path}: {e}")).with_span(&span)
-    }) {
-        Ok(raw) => raw,
-        Err(e) => return e.write_errors().into(),
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let asset_root =
+        PathBuf::from(manifest_dir).join(args.asset_root.as_deref().unwrap_or("assets"));
+    let generated = match (args.file, args.dir) {
+        (Some(file), None) => file_items(&asset_root, &file, args.enums),
+        (None, Some(dir)) => dir_items(&asset_root, &dir, args.enums),
+        _ => Err(darling::Error::custom(
+            "aseprite macro expects either a `file` or a `dir` argument",
+        )),
     };
+    match generated {
+        Ok(generated) => items.extend(generated),
+        Err(e) => return e.with_span(&span).write_errors().into(),
+    }
+
+    quote!(#module).into()
+}
+
+/// The items describing an Aseprite file, its path is relative to the asset root
+fn file_items(
+    asset_root: &Path,
+    file: &str,
+    enums: bool,
+) -> Result<Vec<syn::Item>, darling::Error> {
+    let file_path = asset_root.join(file).display().to_string();
+    let ase_bytes = fs::read(&file_path)
+        .map_err(|e| darling::Error::custom(format!("I/O error for file {file_path}: {e}")))?;
+    // The high level loader of aseprite-loader fails on tilemap cels
+    let raw = parse_file(&ase_bytes)
+        .map_err(|e| darling::Error::custom(format!("Parsing error for file {file_path}: {e}")))?;
+    let (tag_texts, slice_texts) = parse_raw_file(&ase_bytes)
+        .map(|raw_file| user_data_texts(&raw_file))
+        .map_err(|e| darling::Error::custom(format!("Parsing error for file {file_path}: {e}")))?;
 
+    let mut items: Vec<syn::Item> = Vec::new();
     items.push(syn::parse_quote! {
         pub const PATH: &'static str = #file;
     });
+    // Recompiles when the file changes
+    items.push(syn::parse_quote! {
+        const _: &[u8] = include_bytes!(#file_path);
+    });
 
-    let tag_names = raw
-        .tags()
+    let (width, height) = (raw.header.width, raw.header.height);
+    let frame_durations = raw
+        .frames
         .iter()
-        .map(|tag| format_ident!("{}", tag.name.TO_SHOUTY_SNEK_CASE()));
-    let tag_values = raw.tags().iter().map(|tag| &tag.name);
+        .map(|frame| frame.duration)
+        .collect::<Vec<_>>();
+    let frame_count = frame_durations.len();
+    let duration_ms = frame_durations.iter().map(|&d| d as u32).sum::<u32>();
+    items.push(syn::parse_quote! {
+        pub const WIDTH: u16 = #width;
+    });
+    items.push(syn::parse_quote! {
+        pub const HEIGHT: u16 = #height;
+    });
+    items.push(syn::parse_quote! {
+        pub const FRAME_COUNT: usize = #frame_count;
+    });
+    items.push(syn::parse_quote! {
+        /// The duration of each frame in milliseconds
+        pub const FRAME_DURATIONS: [u16; #frame_count] = [#( #frame_durations ),*];
+    });
     items.push(syn::parse_quote! {
+        /// The duration of all frames in milliseconds
+        pub const DURATION_MS: u32 = #duration_ms;
+    });
+
+    let mut tags = Vec::with_capacity(raw.tags.len());
+    let (mut tag_idents, mut tag_modules) = (HashSet::new(), HashSet::new());
+    for tag in raw.tags.iter() {
+        let ident = valid_ident(tag.name.TO_SHOUTY_SNEK_CASE());
+        check_conflict(&mut tag_idents, &ident, "Tag", tag.name, "constant")?;
+        let module = module_ident(tag.name);
+        check_conflict(&mut tag_modules, &module, "Tag", tag.name, "module")?;
+        let value = tag.name;
+        let (start, end) = (*tag.frames.start(), *tag.frames.end());
+        let direction = direction_expr(&tag.animation_direction);
+        let repeat = match tag.animation_repeat {
+            0 => quote!(None),
+            repeat => quote!(Some(#repeat)),
+        };
+        let duration = frame_durations
+            .iter()
+            .take(end as usize + 1)
+            .skip(start as usize)
+            .map(|&d| d as u32)
+            .sum::<u32>();
+        let user_data = tag_texts
+            .get(tag.name)
+            .map(|text| quote!(pub const USER_DATA: &'static str = #text;));
+        tags.push(quote! {
+            pub const #ident: ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new(#value);
+            pub mod #module {
+                pub const RANGE: ::std::ops::RangeInclusive<u16> = #start..=#end;
+                pub const DIRECTION: ::bevy_mod_aseprite::info::AnimationDirection = #direction;
+                pub const REPEAT: ::std::option::Option<u16> = #repeat;
+                pub const DURATION_MS: u32 = #duration;
+                #user_data
+            }
+        });
+    }
+    items.push(syn::parse_quote! {
+        /// Tags, with a module per tag holding its frame range, direction, repeat count,
+        /// duration of a single pass and user data text
         pub mod tags {
-            #( pub const #tag_names: ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new(#tag_values); )*
+            #( #tags )*
         }
     });
 
-    let slice_names = raw
-        .slices()
-        .iter()
-        .map(|slice| format_ident!("{}", slice.name.TO_SHOUTY_SNEK_CASE()));
-    let slice_values = raw.slices().iter().map(|slice| &slice.name);
+    let mut slices = Vec::with_capacity(raw.slices.len());
+    let (mut slice_idents, mut slice_modules) = (HashSet::new(), HashSet::new());
+    for slice in raw.slices.iter() {
+        let ident = valid_ident(slice.name.TO_SHOUTY_SNEK_CASE());
+        check_conflict(&mut slice_idents, &ident, "Slice", slice.name, "constant")?;
+        let module = module_ident(slice.name);
+        check_conflict(&mut slice_modules, &module, "Slice", slice.name, "module")?;
+        let value = slice.name;
+        let user_data = slice_texts.get(slice.name).map(|text| {
+            quote! {
+                pub mod #module {
+                    pub const USER_DATA: &'static str = #text;
+                }
+            }
+        });
+        slices.push(quote! {
+            pub const #ident: ::bevy_mod_aseprite::AsepriteSlice = ::bevy_mod_aseprite::AsepriteSlice::new(#value);
+            #user_data
+        });
+    }
     items.push(syn::parse_quote! {
+        /// Slices, with a module holding the user data text of the ones having one
         pub mod slices {
-            #( pub const #slice_names: ::bevy_mod_aseprite::AsepriteSlice = ::bevy_mod_aseprite::AsepriteSlice::new(#slice_values); )*
+            #( #slices )*
         }
     });
 
-    quote!(#module).into()
+    // Layers in different groups can share their name, the first one is kept
+    let mut layer_idents = HashMap::new();
+    let mut layers = Vec::with_capacity(raw.layers.len());
+    for layer in raw.layers.iter() {
+        let ident = valid_ident(layer.name.TO_SHOUTY_SNEK_CASE());
+        match layer_idents.get(&ident) {
+            Some(&name) if name == layer.name => continue,
+            Some(_) => {
+                return Err(darling::Error::custom(format!(
+                    "Layer {:?} conflicts with another one as constant `{ident}`",
+                    layer.name
+                )));
+            }
+            None => {
+                layer_idents.insert(ident.clone(), layer.name);
+            }
+        }
+        let value = layer.name;
+        layers.push(quote! {
+            pub const #ident: ::bevy_mod_aseprite::AsepriteLayer = ::bevy_mod_aseprite::AsepriteLayer::new(#value);
+        });
+    }
+    items.push(syn::parse_quote! {
+        pub mod layers {
+            #( #layers )*
+        }
+    });
+
+    if enums {
+        items.push(syn::parse_quote! {
+            /// A name that isn't found in the Aseprite file
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub struct UnknownName(pub String);
+        });
+        items.push(syn::parse_quote! {
+            impl ::std::fmt::Display for UnknownName {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    write!(f, "{self:?}")
+                }
+            }
+        });
+        items.push(syn::parse_quote! {
+            impl ::std::error::Error for UnknownName {}
+        });
+        items.extend(name_enum(
+            format_ident!("Tag"),
+            syn::parse_quote!(::bevy_mod_aseprite::AsepriteTag),
+            raw.tags.iter().map(|tag| tag.name),
+        )?);
+        items.extend(name_enum(
+            format_ident!("Slice"),
+            syn::parse_quote!(::bevy_mod_aseprite::AsepriteSlice),
+            raw.slices.iter().map(|slice| slice.name),
+        )?);
+    }
+
+    Ok(items)
+}
+
+/// A module per Aseprite file of a directory and its sub-directories, and their paths
+fn dir_items(asset_root: &Path, dir: &str, enums: bool) -> Result<Vec<syn::Item>, darling::Error> {
+    let mut paths = Vec::new();
+    let mut items = dir_modules(asset_root, dir.trim_end_matches('/'), enums, &mut paths)?;
+    let count = paths.len();
+    items.push(syn::parse_quote! {
+        /// The paths of all the files, e.g. for preloading
+        pub const PATHS: [&'static str; #count] = [#( #paths ),*];
+    });
+    Ok(items)
+}
+
+fn dir_modules(
+    asset_root: &Path,
+    dir: &str,
+    enums: bool,
+    paths: &mut Vec<String>,
+) -> Result<Vec<syn::Item>, darling::Error> {
+    let dir_path = asset_root.join(dir);
+    let mut entries = fs::read_dir(&dir_path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            darling::Error::custom(format!(
+                "I/O error for directory {}: {e}",
+                dir_path.display()
+            ))
+        })?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut items = Vec::new();
+    let mut idents = HashSet::new();
+    for entry in entries {
+        let path = entry.path();
+        let (Some(stem), Some(name)) = (
+            path.file_stem().and_then(|stem| stem.to_str()),
+            entry.file_name().to_str().map(str::to_string),
+        ) else {
+            continue;
+        };
+        let asset_path = format!("{dir}/{name}");
+        let content = if path.is_dir() {
+            dir_modules(asset_root, &asset_path, enums, paths)?
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("ase" | "aseprite")
+        ) {
+            let content = file_items(asset_root, &asset_path, enums)?;
+            paths.push(asset_path.clone());
+            content
+        } else {
+            continue;
+        };
+        let ident = module_ident(if path.is_dir() { &name } else { stem });
+        if !idents.insert(ident.clone()) {
+            return Err(darling::Error::custom(format!(
+                "{asset_path} conflicts with another file as module `{ident}`"
+            )));
+        }
+        items.push(syn::parse_quote! {
+            pub mod #ident {
+                #( #content )*
+            }
+        });
+    }
+    Ok(items)
+}
+
+/// A snake case module name for a file or directory name
+fn module_ident(name: &str) -> Ident {
+    valid_ident(name.to_snake_case())
+}
+
+/// An identifier from a converted name, which may start with a digit or be a keyword
+fn valid_ident(mut name: String) -> Ident {
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    // Keywords
+    if syn::parse_str::<Ident>(&name).is_err() {
+        name.push('_');
+    }
+    format_ident!("{name}")
+}
+
+/// Fails when a name gets the same identifier as a previous one of the same kind
+fn check_conflict(
+    idents: &mut HashSet<Ident>,
+    ident: &Ident,
+    kind: &str,
+    name: &str,
+    item: &str,
+) -> Result<(), darling::Error> {
+    if idents.insert(ident.clone()) {
+        Ok(())
+    } else {
+        Err(darling::Error::custom(format!(
+            "{kind} {name:?} conflicts with another one as {item} `{ident}`"
+        )))
+    }
+}
+
+/// The user data texts of tags and slices, by name
+fn user_data_texts(raw: &RawFile) -> (HashMap<String, String>, HashMap<String, String>) {
+    let (mut tag_texts, mut slice_texts) = (HashMap::new(), HashMap::new());
+    for frame in raw.frames.iter() {
+        // User data chunks follow the tags chunk, one per tag, or the slice chunk
+        let mut tags = Vec::new().into_iter();
+        let mut slice = None;
+        for chunk in frame.chunks.iter() {
+            match chunk {
+                Chunk::Tags(chunk) => {
+                    tags = chunk
+                        .tags
+                        .iter()
+                        .map(|tag| tag.name.to_string())
+                        .collect::<Vec<_>>()
+                        .into_iter();
+                    slice = None;
+                }
+                Chunk::Slice(chunk) => {
+                    tags = Vec::new().into_iter();
+                    slice = Some(chunk.name.to_string());
+                }
+                Chunk::UserData(chunk) => {
+                    let text = chunk.text.filter(|text| !text.is_empty());
+                    if let Some(tag) = tags.next() {
+                        if let Some(text) = text {
+                            tag_texts.insert(tag, text.to_string());
+                        }
+                    } else if let (Some(slice), Some(text)) = (slice.take(), text) {
+                        slice_texts.insert(slice, text.to_string());
+                    }
+                }
+                _ => {
+                    tags = Vec::new().into_iter();
+                    slice = None;
+                }
+            }
+        }
+    }
+    (tag_texts, slice_texts)
+}
+
+fn direction_expr(direction: &AnimationDirection) -> syn::Expr {
+    match direction {
+        AnimationDirection::Forward => {
+            syn::parse_quote!(::bevy_mod_aseprite::info::AnimationDirection::Forward)
+        }
+        AnimationDirection::Reverse => {
+            syn::parse_quote!(::bevy_mod_aseprite::info::AnimationDirection::Reverse)
+        }
+        AnimationDirection::PingPong => {
+            syn::parse_quote!(::bevy_mod_aseprite::info::AnimationDirection::PingPong)
+        }
+        AnimationDirection::PingPongReverse => {
+            syn::parse_quote!(::bevy_mod_aseprite::info::AnimationDirection::PingPongReverse)
+        }
+        AnimationDirection::Unknown(dir) => {
+            syn::parse_quote!(::bevy_mod_aseprite::info::AnimationDirection::Unknown(#dir))
+        }
+    }
+}
+
+/// An enum with a variant per name, convertible to the given name type
+fn name_enum<'a>(
+    ident: Ident,
+    target: syn::Path,
+    names: impl Iterator<Item = &'a str>,
+) -> Result<Vec<syn::Item>, darling::Error> {
+    let names = names.collect::<Vec<_>>();
+    let mut variants = Vec::with_capacity(names.len());
+    let mut seen = HashSet::new();
+    for name in names.iter() {
+        let variant = valid_ident(name.to_upper_camel_case());
+        check_conflict(&mut seen, &variant, &ident.to_string(), name, "variant")?;
+        variants.push(variant);
+    }
+    let count = names.len();
+    Ok(vec![
+        syn::parse_quote! {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub enum #ident {
+                #( #variants, )*
+            }
+        },
+        syn::parse_quote! {
+            impl #ident {
+                pub const ALL: [Self; #count] = [#( Self::#variants, )*];
+
+                pub const fn as_str(&self) -> &'static str {
+                    match *self {
+                        #( Self::#variants => #names, )*
+                    }
+                }
+            }
+        },
+        syn::parse_quote! {
+            impl ::std::convert::From<#ident> for #target {
+                fn from(value: #ident) -> Self {
+                    Self::new(value.as_str())
+                }
+            }
+        },
+        syn::parse_quote! {
+            impl ::std::str::FromStr for #ident {
+                type Err = UnknownName;
+
+                fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                    match s {
+                        #( #names => Ok(Self::#variants), )*
+                        _ => Err(UnknownName(s.to_string())),
+                    }
+                }
+            }
+        },
+    ])
 }
@@ -2,7 +2,8 @@ use bevy::asset::LoadState;
 use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
 use bevy_mod_aseprite::{
-    Aseprite, AsepriteAnimation, AsepriteAsset, AsepritePlugin, AsepriteSystems, AsepriteTag,
+    Aseprite, AsepriteAnimation, AsepriteAsset, AsepritePlugin, AsepriteRepeat, AsepriteSystems,
+    AsepriteTag,
 };
 
 pub mod sprites {
@@ -16,7 +17,7 @@ pub mod sprites {
 pub fn main() {
     App::new()
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
-        .add_plugins(AsepritePlugin)
+        .add_plugins(AsepritePlugin::default())
         .init_resource::<Messages<PlayerChanged>>()
         .init_resource::<AsepriteHandles>()
         .init_state::<AppState>()
@@ -87,7 +88,7 @@ fn setup(
         Sprite {
             image: ase_asset.atlas_texture.clone(),
             texture_atlas: Some(TextureAtlas {
-                index: anim.current_frame(),
+                index: ase_asset.info.atlas_index(anim.current_frame()),
                 layout: ase_asset.atlas_layout.clone(),
             }),
             ..default()
@@ -135,7 +136,8 @@ fn update_player(
     {
         if let Some(new_state) = new_state {
             let info = &aseprites.get(&aseprite.asset).unwrap().info;
-            aseprite.anim = AsepriteAnimation::new(info, new_state.animation_tag());
+            aseprite.anim = AsepriteAnimation::new(info, new_state.animation_tag())
+                .with_repeat(new_state.animation_repeat());
             match new_state {
                 PlayerState::Stand | PlayerState::Attack => {
                     commands.entity(player).remove::<Movements>();
@@ -171,19 +173,14 @@ fn update_player(
 }
 
 fn transition_player(
-    time: Res<Time>,
     player_q: Query<(&PlayerState, &Aseprite), With<Player>>,
-    aseprites: Res<Assets<AsepriteAsset>>,
     mut ev_player_changed: MessageWriter<PlayerChanged>,
 ) -> Result {
     let (&player_state, ase) = player_q.single()?;
-    let ase_asset = aseprites.get(&ase.asset).unwrap();
-    if let PlayerState::Attack = player_state {
-        let remaining_frames = ase.anim.remaining_tag_frames(&ase_asset.info).unwrap();
-        let frame_finished = ase.anim.frame_finished(time.delta());
-        if remaining_frames == 0 && frame_finished {
-            ev_player_changed.write(PlayerState::Stand.into());
-        }
+    if let PlayerState::Attack = player_state
+        && ase.anim.is_finished()
+    {
+        ev_player_changed.write(PlayerState::Stand.into());
     }
     Ok(())
 }
@@ -269,6 +266,13 @@ impl PlayerState {
         }
         .into()
     }
+
+    fn animation_repeat(&self) -> AsepriteRepeat {
+        match &self {
+            Self::Stand | Self::Move => AsepriteRepeat::Forever,
+            Self::Attack => AsepriteRepeat::Times(1),
+        }
+    }
 }
 
 #[derive(Default, Message)]
@@ -1,170 +1,415 @@
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::time::Duration;
 
+use bevy::ecs::component::Mutable;
 use bevy::log;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::{Deserialize, Serialize};
 
+use crate::events::{
+    AsepriteFrameChanged, AsepriteTagFinished, AsepriteTagLooped, AsepriteTagStarted,
+};
 use crate::info::{AnimationDirection, AsepriteInfo};
-use crate::plugin::{Aseprite, AsepriteAsset};
+use crate::plugin::{
+    Aseprite, AsepriteAnchor, AsepriteAsset, AsepriteLayerSprite, AsepritePivot,
+    AsepriteSliceAttachment, AsepriteTimeScale,
+};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct AsepriteAnimation {
     tag: Option<AsepriteTag>,
     current_frame: usize,
     current_timer: Timer,
     forward: bool,
+    started: bool,
+    repeat: AsepriteRepeat,
+    loops_completed: u16,
+    finished: bool,
+    speed: f32,
+    queue: VecDeque<AsepriteTag>,
+    pending: bool, // Waiting for the asset to start
+}
+
+impl Default for AsepriteAnimation {
+    fn default() -> Self {
+        Self {
+            tag: None,
+            current_frame: 0,
+            current_timer: Timer::default(),
+            forward: true,
+            started: false,
+            repeat: AsepriteRepeat::default(),
+            loops_completed: 0,
+            finished: false,
+            speed: 1.0,
+            queue: VecDeque::new(),
+            pending: true,
+        }
+    }
+}
+
+impl From<AsepriteTag> for AsepriteAnimation {
+    fn from(tag: AsepriteTag) -> Self {
+        Self::from_tag(tag)
+    }
+}
+
+impl From<&str> for AsepriteAnimation {
+    fn from(tag: &str) -> Self {
+        Self::from_tag(tag)
+    }
+}
+
+/// How many times an animation is played before holding its last frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteRepeat {
+    /// Uses the repeat count of the tag, looping forever when it isn't set
+    #[default]
+    Tag,
+    /// Loops forever
+    Forever,
+    /// Plays the given number of times, `Times(1)` plays once
+    Times(u16),
+}
+
+/// An error raised by the fallible methods of [`AsepriteAnimation`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsepriteAnimationError {
+    MissingTag(AsepriteTag),
+    InvalidFrame(usize),
+}
+
+impl std::fmt::Display for AsepriteAnimationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for AsepriteAnimationError {}
+
+/// What happened during an animation update
+#[derive(Debug, Default, Clone)]
+struct AnimationUpdate {
+    /// The first frame, when the animation started
+    started: Option<usize>,
+    frame_changed: bool,
+    looped: bool,
+    /// The tag and its last frame played, when the animation finished
+    finished: Option<(Option<AsepriteTag>, usize)>,
 }
 
 impl AsepriteAnimation {
+    /// Creates an animation playing a tag, or all frames when `None`
+    ///
+    /// A tag that isn't found is logged and all frames are played instead, see
+    /// [`AsepriteAnimation::try_new`] to handle it.
     pub fn new<T, U>(info: &AsepriteInfo, tag: T) -> Self
+    where
+        T: Into<Option<U>>,
+        U: Into<AsepriteTag>,
+    {
+        let mut anim = Self::default();
+        anim.start(info, tag.into().map(|t| t.into()));
+        anim
+    }
+
+    /// Creates an animation playing a tag, or all frames when `None`
+    ///
+    /// Returns an error when the tag isn't found.
+    pub fn try_new<T, U>(info: &AsepriteInfo, tag: T) -> Result<Self, AsepriteAnimationError>
     where
         T: Into<Option<U>>,
         U: Into<AsepriteTag>,
     {
         let tag = tag.into().map(|t| t.into());
+        if let Some(tag) = tag.as_ref().filter(|tag| !info.tags.contains_key(*tag)) {
+            return Err(AsepriteAnimationError::MissingTag(tag.clone()));
+        }
+        Ok(Self::new::<_, AsepriteTag>(info, tag))
+    }
+
+    /// Creates an animation starting once its asset is loaded
+    ///
+    /// It is set up by the plugin, along with the sprite's image and atlas, so entities can be
+    /// spawned right away with the handle of the asset:
+    ///
+    /// ```rust
+    /// # use bevy::prelude::*;
+    /// # use bevy_mod_aseprite::{Aseprite, AsepriteAnimation};
+    /// fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+    ///     commands.spawn(Aseprite {
+    ///         asset: asset_server.load("player.ase"),
+    ///         anim: AsepriteAnimation::from_tag("walk"),
+    ///     });
+    /// }
+    /// ```
+    pub fn from_tag(tag: impl Into<AsepriteTag>) -> Self {
+        Self {
+            tag: Some(tag.into()),
+            ..default()
+        }
+    }
+
+    /// Whether the animation waits for its asset to be loaded to start
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Starts a pending animation, keeping its repeat count and pause state
+    fn start_pending(&mut self, info: &AsepriteInfo) {
+        let (repeat, paused) = (self.repeat, self.is_paused());
+        self.start(info, self.tag.clone());
+        self.repeat = repeat;
+        if paused {
+            self.pause();
+        }
+    }
+
+    /// Keeps the playback position valid after the asset was reloaded
+    ///
+    /// The animation restarts without tag when its tag was removed.
+    fn revalidate(&mut self, info: &AsepriteInfo) {
+        if self.pending {
+            return;
+        }
+        self.queue.retain(|tag| info.tags.contains_key(tag));
+        let range = match self.tag.as_ref() {
+            Some(tag) => match info.tags.get(tag) {
+                Some(tag) => *tag.range.start() as usize..=*tag.range.end() as usize,
+                None => {
+                    log::warn!("Tag {tag:?} was removed, playing all frames");
+                    let repeat = self.repeat;
+                    self.start(info, None);
+                    self.repeat = repeat;
+                    return;
+                }
+            },
+            None => 0..=info.frame_count.saturating_sub(1),
+        };
+        self.current_frame = self.current_frame.clamp(*range.start(), *range.end());
+        let elapsed = self.current_timer.elapsed();
+        let duration = frame_duration(info, self.current_frame);
+        self.current_timer.set_duration(duration);
+        self.current_timer.set_elapsed(elapsed.min(duration));
+    }
 
-        let (current_frame, current_timer, forward) = tag
+    /// Starts playing a tag from its first frame, the queue and speed are kept
+    ///
+    /// A tag that isn't found is logged and all frames are played instead.
+    fn start(&mut self, info: &AsepriteInfo, mut tag: Option<AsepriteTag>) {
+        if let Some(missing) = tag.take_if(|tag| !info.tags.contains_key(tag)) {
+            log::error!("Tag {:?} wasn't found, playing all frames", missing);
+        }
+        let (current_frame, forward) = tag
             .as_ref()
-            .and_then(|tag| {
-                info.tags.get(tag).map(|tag| {
-                    let (current_frame, forward) = match tag.direction {
-                        AnimationDirection::Forward
-                        | AnimationDirection::PingPong
-                        | AnimationDirection::Unknown(_) => (*tag.range.start() as usize, true),
-                        AnimationDirection::Reverse | AnimationDirection::PingPongReverse => {
-                            (*tag.range.end() as usize, false)
-                        }
-                    };
-                    let current_timer = Timer::from_seconds(
-                        info.frame_durations[current_frame] as f32 / 1000.0,
-                        TimerMode::Once,
-                    );
-                    (current_frame, current_timer, forward)
-                })
+            .and_then(|tag| info.tags.get(tag))
+            .map(|tag| match tag.direction {
+                AnimationDirection::Forward
+                | AnimationDirection::PingPong
+                | AnimationDirection::Unknown(_) => (*tag.range.start() as usize, true),
+                AnimationDirection::Reverse | AnimationDirection::PingPongReverse => {
+                    (*tag.range.end() as usize, false)
+                }
             })
-            .unwrap_or_else(|| {
-                let current_frame = 0;
-                let forward = true;
-                let current_timer = Timer::from_seconds(
-                    info.frame_durations[current_frame] as f32 / 1000.0,
-                    TimerMode::Once,
-                );
-                (current_frame, current_timer, forward)
-            });
+            .unwrap_or((0, true));
+        let current_timer = Timer::new(frame_duration(info, current_frame), TimerMode::Once);
 
-        Self {
-            tag,
-            current_frame,
-            current_timer,
-            forward,
+        self.tag = tag;
+        self.current_frame = current_frame;
+        self.current_timer = current_timer;
+        self.forward = forward;
+        self.started = false;
+        self.repeat = AsepriteRepeat::default();
+        self.loops_completed = 0;
+        self.finished = false;
+        self.pending = false;
+    }
+
+    /// Sets the playback speed, see [`AsepriteAnimation::set_speed`]
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Overrides the repeat count of the tag, until another tag is played
+    pub fn with_repeat(mut self, repeat: AsepriteRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Advances the animation, returns what happened during this update
+    fn update(&mut self, info: &AsepriteInfo, dt: Duration, time_scale: f32) -> AnimationUpdate {
+        let mut update = AnimationUpdate::default();
+        if self.pending || self.is_paused() {
+            return update;
+        }
+        let speed = self.speed * time_scale;
+        if self.finished {
+            let Some(next) = self.queue.pop_front() else {
+                return update;
+            };
+            // The queued tag shows its first frame for its whole duration
+            self.start(info, Some(next));
+            self.start_direction(info, speed < 0.0);
+            self.started = true;
+            update.started = Some(self.current_frame);
+            update.frame_changed = true;
+            return update;
+        }
+
+        if !self.started {
+            self.started = true;
+            update.frame_changed = self.start_direction(info, speed < 0.0);
+            update.started = Some(self.current_frame);
+        }
+
+        let mut dt =
+            Duration::try_from_secs_f32(dt.as_secs_f32() * speed.abs()).unwrap_or_default();
+        // Time left past the end of a frame is carried over to the next ones
+        loop {
+            let remaining = self.current_timer.remaining();
+            self.current_timer.tick(dt);
+            if !self.current_timer.is_finished() {
+                break;
+            }
+            dt = dt.saturating_sub(remaining);
+            let (previous_frame, previous_forward) = (self.current_frame, self.forward);
+            if self.next_frame(info, speed < 0.0) {
+                self.loops_completed = self.loops_completed.saturating_add(1);
+                if self
+                    .repeat_count(info)
+                    .is_some_and(|count| self.loops_completed >= count)
+                {
+                    update.finished = Some((self.tag.clone(), previous_frame));
+                    if let Some(next) = self.queue.pop_front() {
+                        self.start(info, Some(next));
+                        self.start_direction(info, speed < 0.0);
+                        self.started = true;
+                        let duration = self.current_timer.duration();
+                        self.current_timer.set_elapsed(dt.min(duration));
+                        update.started = Some(self.current_frame);
+                        update.frame_changed = true;
+                    } else {
+                        // Holds the last frame
+                        self.current_frame = previous_frame;
+                        self.forward = previous_forward;
+                        self.finished = true;
+                    }
+                    return update;
+                }
+                update.looped = true;
+            }
+            update.frame_changed = true;
+            self.current_timer = Timer::new(self.current_frame_duration(info), TimerMode::Once);
+            // Frames out of range have no duration, they are shown for an update
+            if dt.is_zero() || self.current_timer.duration().is_zero() {
+                break;
+            }
         }
+        update
     }
 
-    /// Returns whether the frame was changed
-    fn update(&mut self, info: &AsepriteInfo, dt: Duration) -> bool {
-        if self.is_paused() {
+    /// Moves a backwards animation from its first frame to its last one
+    ///
+    /// Returns whether the frame changed. A frame set before starting is kept.
+    fn start_direction(&mut self, info: &AsepriteInfo, backwards: bool) -> bool {
+        if !backwards {
+            return false;
+        }
+        let (first, last) = match self.tag.as_ref().and_then(|tag| info.tags.get(tag)) {
+            Some(tag) if self.forward => (*tag.range.start() as usize, *tag.range.end() as usize),
+            Some(tag) => (*tag.range.end() as usize, *tag.range.start() as usize),
+            None => (0, info.frame_count.saturating_sub(1)),
+        };
+        if self.current_frame != first || first == last {
             return false;
         }
+        self.current_frame = last;
+        self.current_timer
+            .set_duration(self.current_frame_duration(info));
+        true
+    }
 
-        self.current_timer.tick(dt);
-        if self.current_timer.is_finished() {
-            self.next_frame(info);
-            self.current_timer = Timer::from_seconds(
-                self.current_frame_duration(info).as_secs_f32(),
-                TimerMode::Once,
-            );
-            true
-        } else {
-            false
+    /// The number of times the animation is played, `None` when looping forever
+    fn repeat_count(&self, info: &AsepriteInfo) -> Option<u16> {
+        match self.repeat {
+            AsepriteRepeat::Tag => self
+                .tag
+                .as_ref()
+                .and_then(|tag| info.tags.get(tag))
+                .and_then(|tag| tag.repeat)
+                // Endless tags play once when other tags are waiting
+                .or((!self.queue.is_empty()).then_some(1)),
+            AsepriteRepeat::Forever => None,
+            AsepriteRepeat::Times(times) => Some(times.max(1)),
         }
     }
 
-    fn next_frame(&mut self, info: &AsepriteInfo) {
-        match &self.tag {
-            Some(tag) => {
-                let tag = match info.tags.get(tag) {
-                    Some(tag) => tag,
-                    None => {
-                        log::error!("Tag {:?} wasn't found.", tag);
-                        return;
-                    }
-                };
-
-                match tag.direction {
-                    AnimationDirection::Forward => {
-                        let next_frame = self.current_frame + 1;
-                        if tag.range.contains(&(next_frame as u16)) {
-                            self.current_frame = next_frame;
-                        } else {
-                            self.current_frame = *tag.range.start() as usize;
-                        }
-                    }
-                    AnimationDirection::Reverse => {
-                        let next_frame = self.current_frame.checked_sub(1);
-                        if let Some(next_frame) = next_frame {
-                            if tag.range.contains(&(next_frame as u16)) {
-                                self.current_frame = next_frame;
-                            } else {
-                                self.current_frame = *tag.range.end() as usize;
-                            }
-                        } else {
-                            self.current_frame = *tag.range.end() as usize;
-                        }
-                    }
-                    AnimationDirection::PingPong => {
-                        if self.forward {
-                            let next_frame = self.current_frame + 1;
-                            if tag.range.contains(&(next_frame as u16)) {
-                                self.current_frame = next_frame;
-                            } else {
-                                self.current_frame = next_frame.saturating_sub(1);
-                                self.forward = false;
-                            }
-                        } else {
-                            let next_frame = self.current_frame.checked_sub(1);
-                            if let Some(next_frame) = next_frame
-                                && tag.range.contains(&(next_frame as u16))
-                            {
-                                self.current_frame = next_frame
-                            }
-                            self.current_frame += 1;
-                            self.forward = true;
-                        }
-                    }
-                    AnimationDirection::PingPongReverse => {
-                        if self.forward {
-                            let next_frame = self.current_frame.checked_sub(1);
-                            if let Some(next_frame) = next_frame
-                                && tag.range.contains(&(next_frame as u16))
-                            {
-                                self.current_frame = next_frame
-                            }
-                            self.current_frame += 1;
-                            self.forward = false;
-                        } else {
-                            let next_frame = self.current_frame + 1;
-                            if tag.range.contains(&(next_frame as u16)) {
-                                self.current_frame = next_frame;
-                            } else {
-                                self.current_frame = next_frame.saturating_sub(1);
-                                self.forward = true;
-                            }
-                        }
-                    }
-                    AnimationDirection::Unknown(dir) => {
-                        log::warn!("Unknown animation direction {dir}");
-                    }
-                }
+    /// Moves to the next frame, or the previous one when playing backwards
+    ///
+    /// Returns whether the animation looped.
+    /// A tag that isn't found, when the asset was swapped, plays all frames.
+    fn next_frame(&mut self, info: &AsepriteInfo, backwards: bool) -> bool {
+        let Some(tag) = self.tag.as_ref().and_then(|tag| info.tags.get(tag)) else {
+            let frame_count = info.frame_count.max(1);
+            if self.current_frame >= frame_count {
+                self.current_frame = if backwards { frame_count - 1 } else { 0 };
+                return false;
+            }
+            if backwards {
+                self.current_frame = (self.current_frame + frame_count - 1) % frame_count;
+                return self.current_frame == frame_count - 1;
+            } else {
+                self.current_frame = (self.current_frame + 1) % frame_count;
+                return self.current_frame == 0;
+            }
+        };
+
+        let (start, end) = (*tag.range.start() as usize, *tag.range.end() as usize);
+        if !(start..=end).contains(&self.current_frame) {
+            self.current_frame = if self.forward { start } else { end };
+            return false;
+        }
+
+        let ascending = match tag.direction {
+            AnimationDirection::Forward => true,
+            AnimationDirection::Reverse => false,
+            AnimationDirection::PingPong | AnimationDirection::PingPongReverse => self.forward,
+            AnimationDirection::Unknown(dir) => {
+                log::warn!("Unknown animation direction {dir}");
+                return false;
             }
-            None => {
-                self.current_frame = (self.current_frame + 1) % info.frame_count;
+        } != backwards;
+
+        if ascending && self.current_frame < end {
+            self.current_frame += 1;
+            false
+        } else if !ascending && self.current_frame > start {
+            self.current_frame -= 1;
+            false
+        } else {
+            match tag.direction {
+                AnimationDirection::PingPong | AnimationDirection::PingPongReverse => {
+                    // Each direction counts as a repetition, as in Aseprite
+                    self.forward = !self.forward;
+                    self.current_frame = if ascending {
+                        end.saturating_sub(1).max(start)
+                    } else {
+                        (start + 1).min(end)
+                    };
+                }
+                _ => self.current_frame = if ascending { start } else { end },
             }
+            true
         }
     }
 
-    /// The current frame duration
+    /// The current frame duration, zero when the frame is out of range
     pub fn current_frame_duration(&self, info: &AsepriteInfo) -> Duration {
-        Duration::from_millis(info.frame_durations[self.current_frame] as u64)
+        frame_duration(info, self.current_frame)
     }
 
     /// The current frame absolute index
@@ -173,23 +418,38 @@ impl AsepriteAnimation {
     }
 
     /// Set the current frame absolute index
+    ///
+    /// The frame isn't checked, see [`AsepriteAnimation::try_set_frame`]. With an out of range
+    /// frame, the sprite keeps its image until the current frame duration elapses, the animation
+    /// then moves to the start of its tag, or to the first frame without tag.
     pub fn set_current_frame(&mut self, frame: usize) {
         self.current_frame = frame
     }
 
+    /// Set the current frame absolute index
+    ///
+    /// Returns an error when the frame is out of the loaded frames.
+    pub fn try_set_frame(
+        &mut self,
+        info: &AsepriteInfo,
+        frame: usize,
+    ) -> Result<(), AsepriteAnimationError> {
+        if frame >= info.frame_count {
+            return Err(AsepriteAnimationError::InvalidFrame(frame));
+        }
+        self.current_frame = frame;
+        Ok(())
+    }
+
     /// The current frame relative index within the current tag
+    ///
+    /// Returns `None` without tag, or when the tag isn't found.
     pub fn current_tag_frame(&self, info: &AsepriteInfo) -> Option<usize> {
-        self.tag.as_ref().and_then(|tag| {
-            if let Some(tag) = info.tags.get(tag) {
-                Some(
-                    self.current_frame
-                        .saturating_sub(*tag.range.start() as usize),
-                )
-            } else {
-                log::error!("Tag {:?} wasn't found.", tag);
-                None
-            }
-        })
+        let tag = info.tags.get(self.tag.as_ref()?)?;
+        Some(
+            self.current_frame
+                .saturating_sub(*tag.range.start() as usize),
+        )
     }
 
     /// Set current frame relative index within the current tag
@@ -202,15 +462,95 @@ impl AsepriteAnimation {
     }
 
     /// The number of remaning frames in the current tag
+    ///
+    /// Returns `None` without tag, or when the tag isn't found.
     pub fn remaining_tag_frames(&self, info: &AsepriteInfo) -> Option<usize> {
-        self.tag.as_ref().and_then(|tag| {
-            if let Some(tag) = info.tags.get(tag) {
-                Some((*tag.range.end() as usize) - self.current_frame)
-            } else {
-                log::error!("Tag {:?} wasn't found.", tag);
-                None
-            }
-        })
+        let tag = info.tags.get(self.tag.as_ref()?)?;
+        Some((*tag.range.end() as usize).saturating_sub(self.current_frame))
+    }
+
+    /// Overrides the repeat count of the tag, until another tag is played
+    ///
+    /// A finished animation resumes if it has repetitions left. A playing animation finishes at
+    /// the end of its current repetition when it already played as many.
+    pub fn set_repeat(&mut self, info: &AsepriteInfo, repeat: AsepriteRepeat) {
+        self.repeat = repeat;
+        if !self.finished {
+            return;
+        }
+        self.finished = self
+            .repeat_count(info)
+            .is_some_and(|count| self.loops_completed >= count);
+        if !self.finished {
+            // The last repetition was already counted, the next one starts right away
+            self.next_frame(info, self.speed < 0.0);
+            self.current_timer = Timer::new(self.current_frame_duration(info), TimerMode::Once);
+        }
+    }
+
+    /// The repeat count override
+    pub fn repeat(&self) -> AsepriteRepeat {
+        self.repeat
+    }
+
+    /// Returns whether the animation played all its repetitions and holds its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The number of repetitions played so far, each direction counts for ping-pong
+    pub fn loops_completed(&self) -> u16 {
+        self.loops_completed
+    }
+
+    /// Plays a tag right away, clearing the queue
+    ///
+    /// The repeat count override is reset, the tag plays with its own repeat count.
+    pub fn play_tag(&mut self, info: &AsepriteInfo, tag: impl Into<AsepriteTag>) {
+        self.queue.clear();
+        self.start(info, Some(tag.into()));
+    }
+
+    /// Plays a tag right away, then the other one when it finishes
+    ///
+    /// The repeat count override is reset, both tags play with their own repeat count.
+    pub fn play_then(
+        &mut self,
+        info: &AsepriteInfo,
+        tag: impl Into<AsepriteTag>,
+        then: impl Into<AsepriteTag>,
+    ) {
+        self.play_tag(info, tag);
+        self.queue(then);
+    }
+
+    /// Plays a tag once the current one and the ones already queued are finished
+    ///
+    /// While tags are queued, a tag without repeat count stops looping at the end of its current
+    /// repetition. A finished animation starts the queued tag at its next update.
+    pub fn queue(&mut self, tag: impl Into<AsepriteTag>) {
+        self.queue.push_back(tag.into());
+    }
+
+    /// Removes all the queued tags
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
+
+    /// The tags waiting to be played
+    pub fn queued(&self) -> impl Iterator<Item = &AsepriteTag> {
+        self.queue.iter()
+    }
+
+    /// Sets the playback speed multiplier, negative speeds play the animation backwards from
+    /// its last frame
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed
+    }
+
+    /// The playback speed multiplier
+    pub fn speed(&self) -> f32 {
+        self.speed
     }
 
     /// Returns whether the current frame is finished
@@ -257,44 +597,458 @@ impl AsepriteAnimation {
     }
 }
 
-pub fn update_animations(
-    time: Res<Time>,
+/// The duration of a frame, zero when the frame is out of range
+fn frame_duration(info: &AsepriteInfo, frame: usize) -> Duration {
+    info.frame_durations
+        .get(frame)
+        .map(|&duration| Duration::from_millis(duration as u64))
+        .unwrap_or_default()
+}
+
+/// A component playing an [`AsepriteAnimation`] onto the texture atlas of another component
+pub trait AsepritePlayer: Component<Mutability = Mutable> {
+    type Target: Component<Mutability = Mutable>;
+
+    fn asset(&self) -> &Handle<AsepriteAsset>;
+
+    fn anim_mut(&mut self) -> &mut AsepriteAnimation;
+
+    fn anim(&self) -> &AsepriteAnimation;
+
+    fn image_mut(target: &mut Self::Target) -> &mut Handle<Image>;
+
+    fn texture_atlas_mut(target: &mut Self::Target) -> &mut Option<TextureAtlas>;
+}
+
+impl AsepritePlayer for Aseprite {
+    type Target = Sprite;
+
+    fn asset(&self) -> &Handle<AsepriteAsset> {
+        &self.asset
+    }
+
+    fn anim_mut(&mut self) -> &mut AsepriteAnimation {
+        &mut self.anim
+    }
+
+    fn anim(&self) -> &AsepriteAnimation {
+        &self.anim
+    }
+
+    fn image_mut(sprite: &mut Sprite) -> &mut Handle<Image> {
+        &mut sprite.image
+    }
+
+    fn texture_atlas_mut(sprite: &mut Sprite) -> &mut Option<TextureAtlas> {
+        &mut sprite.texture_atlas
+    }
+}
+
+#[cfg(feature = "bevy_ui")]
+impl AsepritePlayer for crate::plugin::AsepriteUi {
+    type Target = ImageNode;
+
+    fn asset(&self) -> &Handle<AsepriteAsset> {
+        &self.asset
+    }
+
+    fn anim_mut(&mut self) -> &mut AsepriteAnimation {
+        &mut self.anim
+    }
+
+    fn anim(&self) -> &AsepriteAnimation {
+        &self.anim
+    }
+
+    fn image_mut(image_node: &mut ImageNode) -> &mut Handle<Image> {
+        &mut image_node.image
+    }
+
+    fn texture_atlas_mut(image_node: &mut ImageNode) -> &mut Option<TextureAtlas> {
+        &mut image_node.texture_atlas
+    }
+}
+
+pub fn setup_animations<P: AsepritePlayer>(
     aseprites: Res<Assets<AsepriteAsset>>,
-    mut aseprites_query: Query<(&mut Aseprite, &mut Sprite)>,
+    mut aseprites_query: Query<(&mut P, &mut P::Target)>,
 ) {
-    for (mut ase, mut sprite) in aseprites_query.iter_mut() {
-        let Some(ase_asset) = aseprites.get(&ase.asset) else {
-            log::error!("Aseprite handle {:?}: no corresponding asset", ase.asset);
+    for (mut ase, mut target) in aseprites_query.iter_mut() {
+        if !ase.anim().is_pending() {
+            continue;
+        }
+        let Some(ase_asset) = aseprites.get(ase.asset()) else {
             continue;
         };
-        if ase.anim.update(&ase_asset.info, time.delta()) {
-            if let Some(atlas) = sprite.texture_atlas.as_mut() {
-                atlas.index = ase.anim.current_frame();
-            } else {
+        ase.anim_mut().start_pending(&ase_asset.info);
+        *P::image_mut(&mut target) = ase_asset.atlas_texture.clone();
+        *P::texture_atlas_mut(&mut target) = Some(TextureAtlas {
+            layout: ase_asset.atlas_layout.clone(),
+            index: ase_asset.info.atlas_index(ase.anim().current_frame()),
+        });
+    }
+}
+
+pub fn reload_animations<P: AsepritePlayer>(
+    mut asset_events: MessageReader<AssetEvent<AsepriteAsset>>,
+    aseprites: Res<Assets<AsepriteAsset>>,
+    mut aseprites_query: Query<(&mut P, &mut P::Target)>,
+) {
+    let modified = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    if modified.is_empty() {
+        return;
+    }
+
+    for (mut ase, mut target) in aseprites_query.iter_mut() {
+        if !modified.contains(&ase.asset().id()) {
+            continue;
+        }
+        let Some(ase_asset) = aseprites.get(ase.asset()) else {
+            continue;
+        };
+        ase.anim_mut().revalidate(&ase_asset.info);
+        if ase.anim().is_pending() {
+            continue;
+        }
+        *P::image_mut(&mut target) = ase_asset.atlas_texture.clone();
+        *P::texture_atlas_mut(&mut target) = Some(TextureAtlas {
+            layout: ase_asset.atlas_layout.clone(),
+            index: ase_asset.info.atlas_index(ase.anim().current_frame()),
+        });
+    }
+}
+
+/// An error logged once per entity, until it is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoggedError {
+    MissingAsset,
+    MissingAtlas,
+}
+
+pub fn update_animations<T: Default + Send + Sync + 'static, P: AsepritePlayer>(
+    mut commands: Commands,
+    time: Res<Time<T>>,
+    time_scale: Res<AsepriteTimeScale>,
+    aseprites: Res<Assets<AsepriteAsset>>,
+    mut aseprites_query: Query<(Entity, &mut P, &mut P::Target)>,
+    mut removed: RemovedComponents<P>,
+    mut logged: Local<HashSet<(Entity, LoggedError)>>,
+) {
+    for entity in removed.read() {
+        logged.remove(&(entity, LoggedError::MissingAsset));
+        logged.remove(&(entity, LoggedError::MissingAtlas));
+    }
+
+    for (entity, mut ase, mut target) in aseprites_query.iter_mut() {
+        if ase.anim().is_pending() {
+            continue;
+        }
+        let Some(ase_asset) = aseprites.get(ase.asset()) else {
+            if logged.insert((entity, LoggedError::MissingAsset)) {
+                log::error!("Aseprite handle {:?}: no corresponding asset", ase.asset());
+            }
+            continue;
+        };
+        logged.remove(&(entity, LoggedError::MissingAsset));
+        let update = ase
+            .anim_mut()
+            .update(&ase_asset.info, time.delta(), time_scale.0);
+
+        let tag = ase.anim().tag().cloned();
+        let frame = ase.anim().current_frame();
+        if let Some((finished_tag, last_frame)) = update.finished {
+            send_event(
+                &mut commands,
+                AsepriteTagFinished {
+                    entity,
+                    tag: finished_tag,
+                    frame: last_frame,
+                },
+            );
+        }
+        if let Some(first_frame) = update.started {
+            send_event(
+                &mut commands,
+                AsepriteTagStarted {
+                    entity,
+                    tag: tag.clone(),
+                    frame: first_frame,
+                },
+            );
+        }
+        if update.looped {
+            send_event(
+                &mut commands,
+                AsepriteTagLooped {
+                    entity,
+                    tag: tag.clone(),
+                    frame,
+                },
+            );
+        }
+        if update.frame_changed {
+            if let Some(atlas) = P::texture_atlas_mut(&mut target).as_mut() {
+                atlas.index = ase_asset.info.atlas_index(frame);
+                logged.remove(&(entity, LoggedError::MissingAtlas));
+            } else if logged.insert((entity, LoggedError::MissingAtlas)) {
                 log::error!(
-                    "Aseprite handle {:?}: sprite has no texture_atlas",
-                    ase.asset
+                    "Aseprite handle {:?}: target has no texture_atlas",
+                    ase.asset()
                 );
             }
+            send_event(&mut commands, AsepriteFrameChanged { entity, tag, frame });
         }
     }
 }
 
-pub fn refresh_animations(mut aseprites_query: Query<(&Aseprite, &mut Sprite), Changed<Aseprite>>) {
-    for (ase, mut sprite) in aseprites_query.iter_mut() {
-        if let Some(atlas) = sprite.texture_atlas.as_mut() {
-            atlas.index = ase.anim.current_frame();
+/// Writes the event as a message and triggers it for observers
+fn send_event<E>(commands: &mut Commands, event: E)
+where
+    E: Message + for<'t> EntityEvent<Trigger<'t>: Default> + Clone,
+{
+    commands.write_message(event.clone());
+    commands.trigger(event);
+}
+
+pub fn refresh_animations<P: AsepritePlayer>(
+    aseprites: Res<Assets<AsepriteAsset>>,
+    mut aseprites_query: Query<(Entity, &P, &mut P::Target), Changed<P>>,
+    mut removed: RemovedComponents<P>,
+    mut logged: Local<HashSet<Entity>>,
+) {
+    for entity in removed.read() {
+        logged.remove(&entity);
+    }
+
+    for (entity, ase, mut target) in aseprites_query.iter_mut() {
+        if ase.anim().is_pending() {
+            continue;
+        }
+        let Some(ase_asset) = aseprites.get(ase.asset()) else {
+            if logged.insert(entity) {
+                log::error!("Aseprite handle {:?}: no corresponding asset", ase.asset());
+            }
+            continue;
+        };
+        logged.remove(&entity);
+        let frame = ase.anim().current_frame();
+        // Frames set out of range keep the previous image until the animation moves on
+        if frame >= ase_asset.info.frame_count {
+            continue;
+        }
+        let index = ase_asset.info.atlas_index(frame);
+        match P::texture_atlas_mut(&mut target) {
+            Some(atlas) => atlas.index = index,
+            // Targets without atlas are set up with the asset's atlas
+            atlas @ None => {
+                *atlas = Some(TextureAtlas {
+                    layout: ase_asset.atlas_layout.clone(),
+                    index,
+                });
+                *P::image_mut(&mut target) = ase_asset.atlas_texture.clone();
+            }
+        }
+    }
+}
+
+/// The anchor of a sprite relative to its full canvas, if managed by the plugin
+fn canvas_anchor(
+    info: &AsepriteInfo,
+    frame: usize,
+    ase_anchor: Option<&AsepriteAnchor>,
+    ase_pivot: Option<&AsepritePivot>,
+) -> Option<Anchor> {
+    if ase_pivot.is_none() && info.frame_trims.is_empty() {
+        return None;
+    }
+    let pivot = ase_pivot.and_then(|pivot| info.slice_pivot(pivot.slice.clone(), frame));
+    // Frames without pivot fall back to the anchor, so the previous pivot isn't kept
+    Some(pivot.unwrap_or_else(|| ase_anchor.map(|a| a.0).unwrap_or_default()))
+}
+
+/// Mirrors an anchor along with the flipped sprite
+fn flip_anchor(anchor: Anchor, flip_x: bool, flip_y: bool) -> Anchor {
+    let mut flipped = anchor.0;
+    if flip_x {
+        flipped.x = -flipped.x;
+    }
+    if flip_y {
+        flipped.y = -flipped.y;
+    }
+    Anchor(flipped)
+}
+
+/// The anchor the sprite is placed with, relative to the full canvas
+fn placed_anchor(
+    info: &AsepriteInfo,
+    frame: usize,
+    sprite: &Sprite,
+    anchor: Anchor,
+    ase_anchor: Option<&AsepriteAnchor>,
+    ase_pivot: Option<&AsepritePivot>,
+) -> Anchor {
+    match canvas_anchor(info, frame, ase_anchor, ase_pivot) {
+        // Anchors managed by the plugin are mirrored when the sprite is flipped
+        Some(canvas_anchor) => flip_anchor(canvas_anchor, sprite.flip_x, sprite.flip_y),
+        // The sprite's anchor is left as is otherwise
+        None => anchor,
+    }
+}
+
+pub fn refresh_anchors(
+    aseprites: Res<Assets<AsepriteAsset>>,
+    mut aseprites_query: Query<
+        (
+            &Aseprite,
+            &Sprite,
+            Option<&AsepriteAnchor>,
+            Option<&AsepritePivot>,
+            &mut Anchor,
+        ),
+        Or<(
+            Changed<Aseprite>,
+            Changed<Sprite>,
+            Changed<AsepriteAnchor>,
+            Changed<AsepritePivot>,
+        )>,
+    >,
+) {
+    for (ase, sprite, ase_anchor, ase_pivot, mut anchor) in aseprites_query.iter_mut() {
+        let Some(ase_asset) = aseprites.get(&ase.asset) else {
+            continue;
+        };
+        let info = &ase_asset.info;
+        let frame = ase.anim.current_frame();
+        let Some(canvas_anchor) = canvas_anchor(info, frame, ase_anchor, ase_pivot) else {
+            continue;
+        };
+        let new_anchor = if info.frame_trims.is_empty() {
+            flip_anchor(canvas_anchor, sprite.flip_x, sprite.flip_y)
         } else {
-            log::error!(
-                "Aseprite handle {:?}: sprite has no texture_atlas",
-                ase.asset
-            );
+            info.trimmed_anchor(frame, canvas_anchor, sprite.flip_x, sprite.flip_y)
+        };
+        anchor.set_if_neq(new_anchor);
+    }
+}
+
+pub fn refresh_layer_sprites(
+    aseprites: Res<Assets<AsepriteAsset>>,
+    parents_query: Query<(&Aseprite, &Sprite, &Anchor), Without<AsepriteLayerSprite>>,
+    mut layers_query: Query<(
+        Entity,
+        &AsepriteLayerSprite,
+        &ChildOf,
+        &mut Sprite,
+        &mut Anchor,
+    )>,
+    mut removed: RemovedComponents<AsepriteLayerSprite>,
+    mut logged: Local<HashSet<Entity>>,
+) {
+    for entity in removed.read() {
+        logged.remove(&entity);
+    }
+
+    for (entity, layer_sprite, child_of, mut sprite, mut anchor) in layers_query.iter_mut() {
+        let Ok((ase, parent_sprite, parent_anchor)) = parents_query.get(child_of.parent()) else {
+            continue;
+        };
+        anchor.set_if_neq(*parent_anchor);
+        let Some(ase_asset) = aseprites.get(&ase.asset) else {
+            continue;
+        };
+        let Some(layer_atlas) = ase_asset.layer_atlas(layer_sprite.layer.clone()) else {
+            if logged.insert(entity) {
+                log::error!(
+                    "Aseprite handle {:?}: layer {:?} has no atlas",
+                    ase.asset,
+                    layer_sprite.layer
+                );
+            }
+            continue;
+        };
+        logged.remove(&entity);
+
+        let frame = ase.anim.current_frame();
+        if frame >= ase_asset.info.frame_count {
+            continue;
+        }
+        let index = ase_asset.info.atlas_index(frame);
+        let up_to_date = sprite.image == layer_atlas.atlas_texture
+            && sprite.texture_atlas.as_ref().is_some_and(|atlas| {
+                atlas.layout == layer_atlas.atlas_layout && atlas.index == index
+            })
+            && sprite.flip_x == parent_sprite.flip_x
+            && sprite.flip_y == parent_sprite.flip_y;
+        if up_to_date {
+            continue;
+        }
+
+        sprite.image = layer_atlas.atlas_texture.clone();
+        sprite.texture_atlas = Some(TextureAtlas {
+            layout: layer_atlas.atlas_layout.clone(),
+            index,
+        });
+        sprite.flip_x = parent_sprite.flip_x;
+        sprite.flip_y = parent_sprite.flip_y;
+    }
+}
+
+pub fn refresh_slice_attachments(
+    aseprites: Res<Assets<AsepriteAsset>>,
+    parents_query: Query<(
+        &Aseprite,
+        &Sprite,
+        &Anchor,
+        Option<&AsepriteAnchor>,
+        Option<&AsepritePivot>,
+    )>,
+    mut slices_query: Query<(&mut AsepriteSliceAttachment, &ChildOf, &mut Transform)>,
+) {
+    for (mut attachment, child_of, mut transform) in slices_query.iter_mut() {
+        let Ok((ase, sprite, anchor, ase_anchor, ase_pivot)) = parents_query.get(child_of.parent())
+        else {
+            continue;
+        };
+        let Some(ase_asset) = aseprites.get(&ase.asset) else {
+            continue;
+        };
+        let frame = ase.anim.current_frame();
+        let anchor = placed_anchor(
+            &ase_asset.info,
+            frame,
+            sprite,
+            *anchor,
+            ase_anchor,
+            ase_pivot,
+        );
+        let rect = ase_asset.info.slice_rect(
+            attachment.slice.clone(),
+            frame,
+            anchor,
+            sprite.flip_x,
+            sprite.flip_y,
+        );
+        if attachment.rect != rect {
+            attachment.rect = rect;
+        }
+        if let Some(rect) = rect {
+            let center = rect.center();
+            if transform.translation.truncate() != center {
+                transform.translation = center.extend(transform.translation.z);
+            }
         }
     }
 }
 
 /// A tag representing an animation
-#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct AsepriteTag(Cow<'static, str>);
 
 impl AsepriteTag {
@@ -348,3 +1102,520 @@ impl AsRef<str> for AsepriteSlice {
         &self.0
     }
 }
+
+/// A name of a layer of a sprite
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsepriteLayer(Cow<'static, str>);
+
+impl AsepriteLayer {
+    pub const fn new(layer: &'static str) -> Self {
+        Self(Cow::Borrowed(layer))
+    }
+}
+
+impl From<&str> for AsepriteLayer {
+    fn from(layer: &str) -> Self {
+        Self(Cow::Owned(layer.to_string()))
+    }
+}
+
+impl From<String> for AsepriteLayer {
+    fn from(layer: String) -> Self {
+        Self(Cow::Owned(layer))
+    }
+}
+
+impl AsRef<str> for AsepriteLayer {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::ops::RangeInclusive;
+
+    use crate::info::{NinePatch, Pivot, SliceKey, Tag};
+
+    use super::*;
+
+    /// Frames of 100ms with the given tags
+    pub(crate) fn info(
+        frame_count: usize,
+        tags: &[(&str, RangeInclusive<u16>, AnimationDirection, Option<u16>)],
+    ) -> AsepriteInfo {
+        AsepriteInfo {
+            frame_count,
+            frame_durations: vec![100; frame_count],
+            tags: tags
+                .iter()
+                .map(|(name, range, direction, repeat)| {
+                    let tag = Tag {
+                        name: name.to_string(),
+                        range: range.clone(),
+                        direction: *direction,
+                        repeat: *repeat,
+                    };
+                    (AsepriteTag::from(*name), tag)
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    /// A canvas of 100x100 pixels with a 1x1 "pivot" slice at (20, 80)
+    fn pivot_info() -> AsepriteInfo {
+        let key = SliceKey {
+            frame_number: 0,
+            x: 20,
+            y: 80,
+            width: 1,
+            height: 1,
+            nine_patch: None,
+            pivot: Some(Pivot { x: 0, y: 0 }),
+        };
+        AsepriteInfo {
+            dimensions: (100, 100),
+            frame_count: 1,
+            frame_durations: vec![100],
+            slices: [(AsepriteSlice::from("pivot"), vec![key])]
+                .into_iter()
+                .collect(),
+            ..default()
+        }
+    }
+
+    /// Plays a frame duration
+    fn step(anim: &mut AsepriteAnimation, info: &AsepriteInfo) -> AnimationUpdate {
+        anim.update(info, Duration::from_millis(100), 1.0)
+    }
+
+    /// The frames played by the following updates
+    pub(crate) fn frames(
+        anim: &mut AsepriteAnimation,
+        info: &AsepriteInfo,
+        count: usize,
+    ) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                step(anim, info);
+                anim.current_frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn forward_tag_loops() {
+        let info = info(5, &[("walk", 1..=3, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "walk");
+        assert_eq!(anim.current_frame(), 1);
+
+        let update = step(&mut anim, &info);
+        assert_eq!(update.started, Some(1));
+        assert!(update.frame_changed && !update.looped);
+        assert_eq!(frames(&mut anim, &info, 1), [3]);
+
+        let update = step(&mut anim, &info);
+        assert!(update.looped && update.finished.is_none());
+        assert_eq!(anim.current_frame(), 1);
+        assert_eq!(anim.loops_completed(), 1);
+    }
+
+    #[test]
+    fn reverse_tag_starts_at_its_end() {
+        let info = info(4, &[("back", 0..=2, AnimationDirection::Reverse, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "back");
+        assert_eq!(anim.current_frame(), 2);
+        assert_eq!(frames(&mut anim, &info, 4), [1, 0, 2, 1]);
+    }
+
+    #[test]
+    fn ping_pong_counts_each_direction() {
+        let info = info(
+            3,
+            &[("swing", 0..=2, AnimationDirection::PingPong, Some(2))],
+        );
+        let mut anim = AsepriteAnimation::new(&info, "swing");
+        assert_eq!(frames(&mut anim, &info, 3), [1, 2, 1]);
+        assert_eq!(anim.loops_completed(), 1);
+        assert_eq!(frames(&mut anim, &info, 1), [0]);
+
+        let update = step(&mut anim, &info);
+        assert_eq!(update.finished, Some((Some("swing".into()), 0)));
+        assert!(anim.is_finished());
+        assert_eq!(anim.current_frame(), 0);
+        assert_eq!(frames(&mut anim, &info, 2), [0, 0]);
+    }
+
+    #[test]
+    fn ping_pong_reverse_starts_backwards() {
+        let info = info(
+            3,
+            &[("swing", 0..=2, AnimationDirection::PingPongReverse, None)],
+        );
+        let mut anim = AsepriteAnimation::new(&info, "swing");
+        assert_eq!(anim.current_frame(), 2);
+        assert_eq!(frames(&mut anim, &info, 4), [1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn repeat_override_holds_last_frame() {
+        let info = info(3, &[("idle", 0..=2, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "idle").with_repeat(AsepriteRepeat::Times(1));
+        assert_eq!(frames(&mut anim, &info, 3), [1, 2, 2]);
+        assert!(anim.is_finished());
+
+        // Resumes with repetitions left, from the start of the next one
+        anim.set_repeat(&info, AsepriteRepeat::Times(2));
+        assert!(!anim.is_finished());
+        assert_eq!(anim.current_frame(), 0);
+        assert_eq!(frames(&mut anim, &info, 3), [1, 2, 2]);
+        assert!(anim.is_finished());
+        assert_eq!(anim.loops_completed(), 2);
+    }
+
+    #[test]
+    fn lowered_repeat_finishes_at_end_of_repetition() {
+        let info = info(3, &[("idle", 0..=2, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "idle");
+        assert_eq!(frames(&mut anim, &info, 4), [1, 2, 0, 1]);
+        assert_eq!(anim.loops_completed(), 1);
+
+        anim.set_repeat(&info, AsepriteRepeat::Times(1));
+        assert!(!anim.is_finished());
+        assert_eq!(frames(&mut anim, &info, 1), [2]);
+        let update = step(&mut anim, &info);
+        assert_eq!(update.finished, Some((Some("idle".into()), 2)));
+        assert!(anim.is_finished());
+        assert_eq!(anim.current_frame(), 2);
+    }
+
+    #[test]
+    fn played_tag_resets_repeat_override() {
+        let info = info(4, &[("idle", 0..=1, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "idle").with_repeat(AsepriteRepeat::Times(1));
+        anim.play_tag(&info, "idle");
+        assert_eq!(anim.repeat(), AsepriteRepeat::Tag);
+        assert_eq!(frames(&mut anim, &info, 3), [1, 0, 1]);
+        assert!(!anim.is_finished());
+    }
+
+    #[test]
+    fn negative_speed_plays_backwards() {
+        let info = info(3, &[("walk", 0..=2, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new(&info, "walk").with_speed(-1.0);
+        assert_eq!(frames(&mut anim, &info, 4), [1, 0, 2, 1]);
+        assert_eq!(anim.loops_completed(), 1);
+
+        // Starts from the last frame
+        let mut anim = AsepriteAnimation::new(&info, "walk").with_speed(-2.0);
+        let update = anim.update(&info, Duration::from_millis(25), 1.0);
+        assert_eq!(update.started, Some(2));
+        assert!(update.frame_changed);
+        assert_eq!(anim.current_frame(), 2);
+        anim.update(&info, Duration::from_millis(25), 1.0);
+        assert_eq!(anim.current_frame(), 1);
+    }
+
+    #[test]
+    fn negative_speed_plays_all_repetitions() {
+        let forward = info(3, &[("walk", 0..=2, AnimationDirection::Forward, Some(1))]);
+        let mut anim = AsepriteAnimation::new(&forward, "walk").with_speed(-1.0);
+        assert_eq!(frames(&mut anim, &forward, 2), [1, 0]);
+        assert!(!anim.is_finished());
+        assert_eq!(frames(&mut anim, &forward, 2), [0, 0]);
+        assert!(anim.is_finished());
+
+        let reverse = info(3, &[("back", 0..=2, AnimationDirection::Reverse, Some(1))]);
+        let mut anim = AsepriteAnimation::new(&reverse, "back").with_speed(-1.0);
+        assert_eq!(frames(&mut anim, &reverse, 3), [1, 2, 2]);
+        assert!(anim.is_finished());
+    }
+
+    #[test]
+    fn fast_speed_skips_frames() {
+        let info = info(20, &[]);
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&info, None).with_speed(10.0);
+        let mut frame_changes = 0;
+        for _ in 0..10 {
+            let update = anim.update(&info, Duration::from_millis(16), 1.0);
+            frame_changes += update.frame_changed as usize;
+        }
+        // 1600ms of animation time are 16 frames of 100ms
+        assert_eq!(anim.current_frame(), 16);
+        assert_eq!(frame_changes, 10);
+        assert_eq!(anim.time_elapsed().as_millis(), 0);
+
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&info, None);
+        anim.update(&info, Duration::from_millis(250), 1.0);
+        assert_eq!(anim.current_frame(), 2);
+        assert_eq!(anim.time_elapsed().as_millis(), 50);
+    }
+
+    #[test]
+    fn paused_and_pending_animations_dont_move() {
+        let info = info(3, &[]);
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&info, None);
+        anim.pause();
+        assert_eq!(frames(&mut anim, &info, 2), [0, 0]);
+        anim.play();
+        assert_eq!(frames(&mut anim, &info, 3), [1, 2, 0]);
+
+        let mut anim = AsepriteAnimation::from_tag("walk");
+        assert!(step(&mut anim, &info).started.is_none());
+        assert_eq!(anim.current_frame(), 0);
+    }
+
+    #[test]
+    fn queued_tag_starts_when_current_finishes() {
+        let info = info(
+            4,
+            &[
+                ("land", 0..=1, AnimationDirection::Forward, None),
+                ("idle", 2..=3, AnimationDirection::Forward, None),
+            ],
+        );
+        let mut anim = AsepriteAnimation::new(&info, "land");
+        anim.queue("idle");
+        assert_eq!(frames(&mut anim, &info, 1), [1]);
+
+        // Endless tags play once while others are queued
+        let update = step(&mut anim, &info);
+        assert_eq!(update.finished, Some((Some("land".into()), 1)));
+        assert_eq!(update.started, Some(2));
+        assert_eq!(anim.tag(), Some(&"idle".into()));
+        assert_eq!(anim.queued().count(), 0);
+
+        // The last tag loops forever
+        assert_eq!(frames(&mut anim, &info, 3), [3, 2, 3]);
+        assert!(!anim.is_finished());
+    }
+
+    #[test]
+    fn queued_tag_starts_after_endless_loops() {
+        let info = info(
+            5,
+            &[
+                ("idle", 0..=1, AnimationDirection::Forward, None),
+                ("attack", 2..=4, AnimationDirection::Forward, None),
+            ],
+        );
+        let mut anim = AsepriteAnimation::new(&info, "idle");
+        assert_eq!(frames(&mut anim, &info, 5), [1, 0, 1, 0, 1]);
+        assert_eq!(anim.loops_completed(), 2);
+
+        // The current repetition is played to its end
+        anim.queue("attack");
+        let update = step(&mut anim, &info);
+        assert_eq!(update.finished, Some((Some("idle".into()), 1)));
+        assert_eq!(update.started, Some(2));
+        assert_eq!(anim.tag(), Some(&"attack".into()));
+    }
+
+    #[test]
+    fn finished_animation_starts_queued_tag() {
+        let info = info(
+            4,
+            &[
+                ("once", 0..=1, AnimationDirection::Forward, Some(1)),
+                ("idle", 2..=3, AnimationDirection::Forward, None),
+            ],
+        );
+        let mut anim = AsepriteAnimation::new(&info, "once");
+        assert_eq!(frames(&mut anim, &info, 2), [1, 1]);
+        assert!(anim.is_finished());
+
+        anim.queue("idle");
+        let update = step(&mut anim, &info);
+        assert!(update.frame_changed);
+        assert_eq!(update.started, Some(2));
+        assert_eq!(anim.current_frame(), 2);
+    }
+
+    #[test]
+    fn slice_rect_follows_placed_anchor() {
+        let info = pivot_info();
+        let pivot = AsepritePivot::new("pivot");
+        let slice_rect = |flip_x, flip_y| {
+            let sprite = Sprite {
+                flip_x,
+                flip_y,
+                ..default()
+            };
+            let anchor = placed_anchor(&info, 0, &sprite, Anchor::CENTER, None, Some(&pivot));
+            let rect = info.slice_rect("pivot", 0, anchor, flip_x, flip_y).unwrap();
+            (rect.min.round(), rect.max.round())
+        };
+
+        assert_eq!(
+            slice_rect(false, false),
+            (Vec2::new(0.0, -1.0), Vec2::new(1.0, 0.0))
+        );
+        // The pivot stays at the origin, the slice is mirrored around it
+        assert_eq!(
+            slice_rect(true, false),
+            (Vec2::new(-1.0, -1.0), Vec2::new(0.0, 0.0))
+        );
+        assert_eq!(
+            slice_rect(true, true),
+            (Vec2::new(-1.0, 0.0), Vec2::new(0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn missing_pivot_falls_back_to_anchor() {
+        let mut info = pivot_info();
+        info.frame_count = 2;
+        info.frame_durations = vec![100, 100];
+        let keys = info.slices.get_mut(&AsepriteSlice::from("pivot")).unwrap();
+        let key = SliceKey {
+            frame_number: 1,
+            pivot: None,
+            ..keys[0]
+        };
+        keys.push(key);
+        let pivot = AsepritePivot::new("pivot");
+        let ase_anchor = AsepriteAnchor(Anchor::BOTTOM_CENTER);
+
+        let pivot_anchor = info.slice_pivot("pivot", 0).unwrap();
+        assert_eq!(
+            canvas_anchor(&info, 0, Some(&ase_anchor), Some(&pivot)),
+            Some(pivot_anchor)
+        );
+        // The untrimmed sprite doesn't keep the pivot of the previous frame
+        assert_eq!(
+            canvas_anchor(&info, 1, Some(&ase_anchor), Some(&pivot)),
+            Some(Anchor::BOTTOM_CENTER)
+        );
+        assert_eq!(
+            canvas_anchor(&info, 1, None, Some(&pivot)),
+            Some(Anchor::CENTER)
+        );
+        assert_eq!(canvas_anchor(&info, 1, Some(&ase_anchor), None), None);
+    }
+
+    #[test]
+    fn nine_patch_borders_follow_trimmed_rect() {
+        // A 10x10 panel with borders of 2 pixels, its left column is transparent
+        let key = SliceKey {
+            frame_number: 0,
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+            nine_patch: Some(NinePatch {
+                x: 2,
+                y: 2,
+                width: 6,
+                height: 6,
+            }),
+            pivot: None,
+        };
+        let mut info = AsepriteInfo {
+            dimensions: (10, 10),
+            frame_count: 1,
+            frame_durations: vec![100],
+            slices: [(AsepriteSlice::from("panel"), vec![key])]
+                .into_iter()
+                .collect(),
+            ..default()
+        };
+        let border = |info: &AsepriteInfo| {
+            let border = info.slice_texture_slicer("panel", 0).unwrap().border;
+            (border.min_inset, border.max_inset)
+        };
+        assert_eq!(border(&info), (Vec2::splat(2.0), Vec2::splat(2.0)));
+
+        info.frame_trims = vec![URect::new(1, 0, 10, 10)];
+        let rect = info.slice_atlas_rect("panel", 0).unwrap();
+        assert_eq!(rect.size(), Vec2::new(9.0, 10.0));
+        assert_eq!(border(&info), (Vec2::new(1.0, 2.0), Vec2::splat(2.0)));
+    }
+
+    #[test]
+    fn unmanaged_anchor_is_used_as_is() {
+        let info = pivot_info();
+        let sprite = Sprite {
+            flip_x: true,
+            ..default()
+        };
+        let anchor = placed_anchor(&info, 0, &sprite, Anchor::TOP_LEFT, None, None);
+        assert_eq!(anchor, Anchor::TOP_LEFT);
+        // The flipped slice is 20 pixels from the right edge
+        let rect = info.slice_rect("pivot", 0, anchor, true, false).unwrap();
+        assert_eq!(
+            (rect.min, rect.max),
+            (Vec2::new(79.0, -81.0), Vec2::new(80.0, -80.0))
+        );
+    }
+
+    #[test]
+    fn reload_clamps_frame_to_shorter_file() {
+        let loaded = info(5, &[("walk", 1..=4, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&loaded, None);
+        anim.set_current_frame(4);
+        anim.revalidate(&info(3, &[]));
+        assert_eq!(anim.current_frame(), 2);
+
+        let mut anim = AsepriteAnimation::new(&loaded, "walk");
+        anim.set_current_frame(4);
+        let reloaded = info(5, &[("walk", 1..=2, AnimationDirection::Forward, None)]);
+        anim.revalidate(&reloaded);
+        assert_eq!(anim.current_frame(), 2);
+        assert_eq!(anim.tag(), Some(&"walk".into()));
+    }
+
+    #[test]
+    fn reload_without_tag_plays_all_frames() {
+        let loaded = info(5, &[("walk", 1..=4, AnimationDirection::Forward, None)]);
+        let mut anim =
+            AsepriteAnimation::new(&loaded, "walk").with_repeat(AsepriteRepeat::Times(2));
+        assert_eq!(frames(&mut anim, &loaded, 2), [2, 3]);
+
+        let reloaded = info(5, &[]);
+        anim.revalidate(&reloaded);
+        assert_eq!(anim.tag(), None);
+        assert_eq!(anim.current_frame(), 0);
+        assert_eq!(anim.repeat(), AsepriteRepeat::Times(2));
+        assert_eq!(frames(&mut anim, &reloaded, 2), [1, 2]);
+    }
+
+    #[test]
+    fn reload_caps_elapsed_time_to_frame_duration() {
+        let loaded = info(3, &[]);
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&loaded, None);
+        // A sixteenth of a second is exact once scaled by the speed
+        let elapsed = Duration::from_micros(62_500);
+        anim.update(&loaded, elapsed, 1.0);
+        assert_eq!(anim.time_elapsed(), elapsed);
+
+        // The elapsed time is kept when the frame is long enough
+        let mut reloaded = info(3, &[]);
+        reloaded.frame_durations[0] = 200;
+        anim.revalidate(&reloaded);
+        assert_eq!(anim.time_elapsed(), elapsed);
+
+        reloaded.frame_durations[0] = 50;
+        anim.revalidate(&reloaded);
+        assert_eq!(anim.time_elapsed(), Duration::from_millis(50));
+        assert_eq!(
+            anim.current_frame_duration(&reloaded),
+            Duration::from_millis(50)
+        );
+        // The frame is over, the next update moves on
+        anim.update(&reloaded, Duration::from_millis(1), 1.0);
+        assert_eq!(anim.current_frame(), 1);
+    }
+
+    #[test]
+    fn out_of_range_frame_restarts() {
+        let info = info(35, &[("idle", 2..=3, AnimationDirection::Forward, None)]);
+        let mut anim = AsepriteAnimation::new::<_, AsepriteTag>(&info, None);
+        anim.set_current_frame(40);
+        assert_eq!(frames(&mut anim, &info, 2), [0, 1]);
+
+        let mut anim = AsepriteAnimation::new(&info, "idle");
+        anim.set_current_frame(40);
+        assert_eq!(frames(&mut anim, &info, 2), [2, 3]);
+    }
+}
@@ -0,0 +1,44 @@
+//! Events sent while [`Aseprite`][crate::Aseprite] animations are playing.
+//!
+//! Each event is both written as a [`Message`] and triggered as an [`EntityEvent`], so it
+//! can be read with a [`MessageReader`] or observed on the animated entity.
+
+use bevy::prelude::*;
+
+use crate::anim::AsepriteTag;
+
+/// Sent when an animation starts playing
+#[derive(Message, EntityEvent, Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteTagStarted {
+    pub entity: Entity,
+    pub tag: Option<AsepriteTag>,
+    /// The absolute index of the first frame
+    pub frame: usize,
+}
+
+/// Sent when an animation moves to another frame
+#[derive(Message, EntityEvent, Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteFrameChanged {
+    pub entity: Entity,
+    pub tag: Option<AsepriteTag>,
+    /// The absolute index of the new frame
+    pub frame: usize,
+}
+
+/// Sent when an animation goes back to its first frame, or changes direction for ping-pong
+#[derive(Message, EntityEvent, Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteTagLooped {
+    pub entity: Entity,
+    pub tag: Option<AsepriteTag>,
+    /// The absolute index of the frame the loop starts with
+    pub frame: usize,
+}
+
+/// Sent when an animation plays its last repetition and holds its last frame
+#[derive(Message, EntityEvent, Debug, Clone, PartialEq, Eq)]
+pub struct AsepriteTagFinished {
+    pub entity: Entity,
+    pub tag: Option<AsepriteTag>,
+    /// The absolute index of the last frame played
+    pub frame: usize,
+}
@@ -8,23 +8,411 @@
 //! [aseprite-loader]: https://crates.io/crates/aseprite-loader
 //! [ase-specs]: https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
 
+use bevy::color::Color;
+use bevy::math::{IRect, IVec2, Rect, URect, Vec2};
 use bevy::platform::collections::HashMap;
+use bevy::sprite::{Anchor, BorderRect, TextureSlicer};
 
+pub use aseprite_loader::binary::blend_mode::BlendMode;
+pub use aseprite_loader::binary::chunks::layer::LayerType;
 pub use aseprite_loader::binary::chunks::slice::{NinePatch, Pivot, SliceKey};
 pub use aseprite_loader::binary::chunks::tags::AnimationDirection;
 pub use aseprite_loader::binary::palette::Palette;
 pub use aseprite_loader::binary::scalars::{Byte, Double, Dword, Long};
 pub use aseprite_loader::loader::Tag;
 
-use crate::anim::{AsepriteSlice, AsepriteTag};
+use crate::anim::{AsepriteLayer, AsepriteSlice, AsepriteTag};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct AsepriteInfo {
     pub dimensions: (u16, u16),
     pub tags: HashMap<AsepriteTag, Tag>,
     pub slices: HashMap<AsepriteSlice, Vec<SliceKey>>,
+    pub layers: Vec<Layer>, // From bottom to top
     pub frame_count: usize,
     pub palette: Option<Palette>,
+    pub palette_size: usize, // Number of colors in use, `Palette::colors` is padded to 256
     pub transparent_palette: Byte,
-    pub frame_durations: Vec<u16>, // In milliseconds
+    pub frame_durations: Vec<u16>,       // In milliseconds
+    pub frame_atlas_indices: Vec<usize>, // Identical frames share the same atlas index
+    pub frame_trims: Vec<URect>,         // Empty unless loaded with trimming enabled
+    pub user_data: UserData,             // Attached to the sprite itself
+    pub tag_user_data: HashMap<AsepriteTag, UserData>,
+    pub slice_user_data: HashMap<AsepriteSlice, UserData>,
+    pub cel_user_data: HashMap<(usize, usize), UserData>, // By frame and index in `layers`
+}
+
+impl AsepriteInfo {
+    /// The atlas index of an absolute frame index
+    pub fn atlas_index(&self, frame: usize) -> usize {
+        self.frame_atlas_indices
+            .get(frame)
+            .copied()
+            .unwrap_or(frame)
+    }
+
+    /// The non-transparent bounds of a frame within the canvas, if trimmed
+    pub fn frame_trim(&self, frame: usize) -> Option<URect> {
+        self.frame_trims.get(frame).copied()
+    }
+
+    /// Converts an anchor relative to the canvas into an anchor relative to the trimmed frame
+    ///
+    /// Returns the canvas anchor as is when the frame isn't trimmed.
+    pub fn trimmed_anchor(
+        &self,
+        frame: usize,
+        anchor: Anchor,
+        flip_x: bool,
+        flip_y: bool,
+    ) -> Anchor {
+        let Some(trim) = self.frame_trim(frame) else {
+            return anchor;
+        };
+        let canvas = Vec2::new(self.dimensions.0 as f32, self.dimensions.1 as f32);
+        let (min, size) = (trim.min.as_vec2(), trim.size().as_vec2());
+        // Positions from the top left corner, y pointing down
+        let point = Vec2::new(anchor.x + 0.5, 0.5 - anchor.y) * canvas;
+        let mut trimmed = Vec2::new(
+            (point.x - min.x) / size.x - 0.5,
+            0.5 - (point.y - min.y) / size.y,
+        );
+        if flip_x {
+            trimmed.x = -trimmed.x;
+        }
+        if flip_y {
+            trimmed.y = -trimmed.y;
+        }
+        Anchor(trimmed)
+    }
+
+    /// The key of a slice applying to an absolute frame index
+    ///
+    /// A key stays valid until the next one, `None` is returned before the first key.
+    pub fn slice_at(&self, slice: impl Into<AsepriteSlice>, frame: usize) -> Option<&SliceKey> {
+        self.slices
+            .get(&slice.into())?
+            .iter()
+            .take_while(|key| key.frame_number as usize <= frame)
+            .last()
+    }
+
+    /// The bounds of a slice at an absolute frame index, relative to the sprite's anchor
+    ///
+    /// The anchor is relative to the full canvas and the y axis points up, so the rect can be
+    /// used as is in the sprite's local space.
+    pub fn slice_rect(
+        &self,
+        slice: impl Into<AsepriteSlice>,
+        frame: usize,
+        anchor: Anchor,
+        flip_x: bool,
+        flip_y: bool,
+    ) -> Option<Rect> {
+        let key = self.slice_at(slice, frame)?;
+        let min = Vec2::new(key.x as f32, key.y as f32);
+        let max = min + Vec2::new(key.width as f32, key.height as f32);
+        Some(Rect::from_corners(
+            self.canvas_to_local(min, anchor, flip_x, flip_y),
+            self.canvas_to_local(max, anchor, flip_x, flip_y),
+        ))
+    }
+
+    /// The pivot of a slice at an absolute frame index, as an anchor relative to the canvas
+    pub fn slice_pivot(&self, slice: impl Into<AsepriteSlice>, frame: usize) -> Option<Anchor> {
+        let key = self.slice_at(slice, frame)?;
+        let pivot = key.pivot.as_ref()?;
+        let canvas = Vec2::new(self.dimensions.0 as f32, self.dimensions.1 as f32);
+        let point = Vec2::new((key.x + pivot.x) as f32, (key.y + pivot.y) as f32);
+        Some(Anchor(Vec2::new(
+            point.x / canvas.x - 0.5,
+            0.5 - point.y / canvas.y,
+        )))
+    }
+
+    /// The bounds of a slice at an absolute frame index, relative to the frame in the atlas
+    ///
+    /// It can be used as [`Sprite::rect`] along with the frame's atlas index. Slices reaching
+    /// outside of trimmed frames are cropped.
+    ///
+    /// [`Sprite::rect`]: bevy::sprite::Sprite::rect
+    pub fn slice_atlas_rect(&self, slice: impl Into<AsepriteSlice>, frame: usize) -> Option<Rect> {
+        let key = self.slice_at(slice, frame)?;
+        let min = Vec2::new(key.x as f32, key.y as f32);
+        let rect = Rect::from_corners(min, min + Vec2::new(key.width as f32, key.height as f32));
+        Some(match self.frame_trim(frame) {
+            Some(trim) => {
+                let trim = Rect::from_corners(trim.min.as_vec2(), trim.max.as_vec2());
+                let rect = rect.intersect(trim);
+                Rect::from_corners(rect.min - trim.min, rect.max - trim.min)
+            }
+            None => rect,
+        })
+    }
+
+    /// The slicing of a nine-patch slice at an absolute frame index
+    ///
+    /// Returns `None` when the slice has no center. The border is relative to the slice bounds,
+    /// so the slicer is meant to be used with [`AsepriteInfo::slice_atlas_rect`]. Borders of
+    /// slices reaching outside of trimmed frames are cropped along with the bounds.
+    pub fn slice_texture_slicer(
+        &self,
+        slice: impl Into<AsepriteSlice>,
+        frame: usize,
+    ) -> Option<TextureSlicer> {
+        let key = self.slice_at(slice, frame)?;
+        let center = key.nine_patch.as_ref()?;
+        let min = Vec2::new(key.x as f32, key.y as f32);
+        let mut rect =
+            Rect::from_corners(min, min + Vec2::new(key.width as f32, key.height as f32));
+        if let Some(trim) = self.frame_trim(frame) {
+            rect = rect.intersect(Rect::from_corners(trim.min.as_vec2(), trim.max.as_vec2()));
+        }
+        let center_min = min + Vec2::new(center.x as f32, center.y as f32);
+        let center_max = center_min + Vec2::new(center.width as f32, center.height as f32);
+        Some(TextureSlicer {
+            border: BorderRect {
+                min_inset: (center_min - rect.min).clamp(Vec2::ZERO, rect.size()),
+                max_inset: (rect.max - center_max).clamp(Vec2::ZERO, rect.size()),
+            },
+            ..Default::default()
+        })
+    }
+
+    /// Converts a position in pixels from the top left corner of the canvas into the
+    /// sprite's local space
+    ///
+    /// The anchor is the one the sprite is placed with, so it is already mirrored when the
+    /// sprite is flipped.
+    pub fn canvas_to_local(&self, point: Vec2, anchor: Anchor, flip_x: bool, flip_y: bool) -> Vec2 {
+        let canvas = Vec2::new(self.dimensions.0 as f32, self.dimensions.1 as f32);
+        let mut point = point;
+        if flip_x {
+            point.x = canvas.x - point.x;
+        }
+        if flip_y {
+            point.y = canvas.y - point.y;
+        }
+        Vec2::new(
+            point.x - (anchor.x + 0.5) * canvas.x,
+            (0.5 - anchor.y) * canvas.y - point.y,
+        )
+    }
+
+    /// The colors of the palette, empty unless the sprite is in indexed mode
+    ///
+    /// They can be used to swap palettes, see [`AsepriteRemap::from_palette`].
+    ///
+    /// [`AsepriteRemap::from_palette`]: crate::AsepriteRemap::from_palette
+    pub fn palette_colors(&self) -> Vec<Color> {
+        self.palette
+            .iter()
+            .flat_map(|palette| palette.colors.iter().take(self.palette_size))
+            .map(|c| Color::srgba_u8(c.red, c.green, c.blue, c.alpha))
+            .collect()
+    }
+
+    /// Finds a layer by path or by name
+    ///
+    /// The path of a layer joins the names of its parent groups and its own with `/`, such as
+    /// `back/hand`, it is the layer's name at the top level. A name that isn't a path finds the
+    /// first layer with this name, from the bottom one.
+    pub fn layer(&self, layer: impl Into<AsepriteLayer>) -> Option<&Layer> {
+        self.layer_index(layer).map(|index| &self.layers[index])
+    }
+
+    /// The index in [`AsepriteInfo::layers`] of a layer found by path or by name
+    pub fn layer_index(&self, layer: impl Into<AsepriteLayer>) -> Option<usize> {
+        let layer = layer.into();
+        self.layers
+            .iter()
+            .position(|l| layer_path(&self.layers, l) == layer)
+            .or_else(|| self.layers.iter().position(|l| l.name == layer))
+    }
+
+    /// The path of a layer, the names of its parent groups and its own joined with `/`
+    pub fn layer_path(&self, layer: &Layer) -> AsepriteLayer {
+        layer_path(&self.layers, layer)
+    }
+
+    /// The layer's parent group
+    pub fn parent_layer(&self, layer: &Layer) -> Option<&Layer> {
+        layer.parent.and_then(|index| self.layers.get(index))
+    }
+
+    /// The user data of a tag
+    pub fn tag_user_data(&self, tag: impl Into<AsepriteTag>) -> Option<&UserData> {
+        self.tag_user_data.get(&tag.into())
+    }
+
+    /// The user data of a slice
+    pub fn slice_user_data(&self, slice: impl Into<AsepriteSlice>) -> Option<&UserData> {
+        self.slice_user_data.get(&slice.into())
+    }
+
+    /// The user data of a layer, found by path or by name
+    pub fn layer_user_data(&self, layer: impl Into<AsepriteLayer>) -> Option<&UserData> {
+        self.layer(layer).map(|layer| &layer.user_data)
+    }
+
+    /// The user data of the cel of a layer, found by path or by name, at an absolute frame index
+    pub fn cel_user_data(
+        &self,
+        frame: usize,
+        layer: impl Into<AsepriteLayer>,
+    ) -> Option<&UserData> {
+        let index = self.layer_index(layer)?;
+        self.cel_user_data.get(&(frame, index))
+    }
+
+    /// The user data of all the cels of a frame, from the top layer to the bottom one
+    pub fn frame_user_data(&self, frame: usize) -> impl Iterator<Item = (&Layer, &UserData)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(move |(index, layer)| {
+                self.cel_user_data
+                    .get(&(frame, index))
+                    .map(|user_data| (layer, user_data))
+            })
+    }
+
+    /// A property of the cels of a frame, taken from the topmost layer defining it
+    pub fn frame_property(&self, frame: usize, name: &str) -> Option<&UserProperty> {
+        self.frame_user_data(frame)
+            .find_map(|(_, user_data)| user_data.property(name))
+    }
+}
+
+/// The names of the parent groups of a layer and its own, joined with `/`
+pub(crate) fn layer_path(layers: &[Layer], layer: &Layer) -> AsepriteLayer {
+    let mut names = std::iter::successors(Some(layer), |layer| {
+        layer.parent.and_then(|parent| layers.get(parent))
+    })
+    .map(|layer| layer.name.as_ref())
+    .collect::<Vec<_>>();
+    names.reverse();
+    AsepriteLayer::from(names.join("/"))
+}
+
+/// A layer of the Aseprite file
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: AsepriteLayer,
+    /// Opacity in the range `0..=255`
+    pub opacity: Byte,
+    pub blend_mode: BlendMode,
+    /// Whether the layer is visible in the Aseprite file
+    pub visible: bool,
+    pub layer_type: LayerType,
+    /// Index of the parent group in [`AsepriteInfo::layers`]
+    pub parent: Option<usize>,
+    pub user_data: UserData,
+}
+
+impl Layer {
+    pub fn is_group(&self) -> bool {
+        self.layer_type == LayerType::Group
+    }
+}
+
+/// User data attached to the sprite, a tag, a layer, a cel or a slice
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UserData {
+    pub text: Option<String>,
+    pub color: Option<Color>,
+    /// Custom properties, excluding the ones set by extensions
+    pub properties: HashMap<String, UserProperty>,
+}
+
+impl UserData {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.color.is_none() && self.properties.is_empty()
+    }
+
+    pub fn property(&self, name: &str) -> Option<&UserProperty> {
+        self.properties.get(name)
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        self.property(name).and_then(UserProperty::as_bool)
+    }
+
+    pub fn int(&self, name: &str) -> Option<i64> {
+        self.property(name).and_then(UserProperty::as_int)
+    }
+
+    pub fn float(&self, name: &str) -> Option<f64> {
+        self.property(name).and_then(UserProperty::as_float)
+    }
+
+    pub fn str(&self, name: &str) -> Option<&str> {
+        self.property(name).and_then(UserProperty::as_str)
+    }
+}
+
+/// A custom property value, integers and floats are widened
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserProperty {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    String(String),
+    Point(IVec2),
+    Size(IVec2),
+    Rect(IRect),
+    Vector(Vec<UserProperty>),
+    Map(HashMap<String, UserProperty>),
+    Uuid(u128),
+}
+
+impl UserProperty {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer property, unsigned ones are converted when they fit
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            Self::Uint(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    /// The value of a number property
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as f64),
+            Self::Uint(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<&[UserProperty]> {
+        match self {
+            Self::Vector(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&HashMap<String, UserProperty>> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
 }
@@ -2,31 +2,71 @@
 #![doc = include_str!("../README.md")]
 
 mod anim;
+pub mod events;
 pub mod info;
 mod loader;
+mod palette;
 mod plugin;
+mod state_machine;
+pub mod tilemap;
 
-pub use crate::anim::{AsepriteAnimation, AsepriteSlice, AsepriteTag};
+pub use crate::anim::{
+    AsepriteAnimation, AsepriteAnimationError, AsepriteLayer, AsepriteRepeat, AsepriteSlice,
+    AsepriteTag,
+};
+pub use crate::events::{
+    AsepriteFrameChanged, AsepriteTagFinished, AsepriteTagLooped, AsepriteTagStarted,
+};
 pub use crate::info::AsepriteInfo;
-pub use crate::loader::{AsepriteLoader, AsepriteLoaderError};
-pub use crate::plugin::{Aseprite, AsepriteAsset, AsepritePlugin, AsepriteSystems};
+pub use crate::loader::{
+    AsepriteFrames, AsepriteLayerSplit, AsepriteLayers, AsepriteLoader, AsepriteLoaderError,
+    AsepriteLoaderSettings,
+};
+pub use crate::palette::{AsepriteRecolors, AsepriteRemap};
+#[cfg(feature = "bevy_ui")]
+pub use crate::plugin::AsepriteUi;
+pub use crate::plugin::{
+    Aseprite, AsepriteAnchor, AsepriteAsset, AsepriteLayerAtlas, AsepriteLayerSprite,
+    AsepritePivot, AsepritePlugin, AsepriteSliceAttachment, AsepriteSystems, AsepriteTimeScale,
+};
+pub use crate::state_machine::{AsepriteCondition, AsepriteStateMachine, AsepriteTransition};
+pub use crate::tilemap::{
+    AsepriteTile, AsepriteTileGrid, AsepriteTilemap, AsepriteTilemapSprites, AsepriteTileset,
+};
 
 pub mod prelude {
+    #[cfg(feature = "bevy_ui")]
+    pub use super::AsepriteUi;
     pub use super::{
-        Aseprite, AsepriteAnimation, AsepriteAsset, AsepriteInfo, AsepritePlugin, AsepriteSlice,
-        AsepriteSystems, AsepriteTag, aseprite,
+        Aseprite, AsepriteAnchor, AsepriteAnimation, AsepriteAsset, AsepriteFrameChanged,
+        AsepriteInfo, AsepriteLayer, AsepriteLayerSprite, AsepriteLoaderSettings, AsepritePivot,
+        AsepritePlugin, AsepriteRepeat, AsepriteSlice, AsepriteSliceAttachment,
+        AsepriteStateMachine, AsepriteSystems, AsepriteTag, AsepriteTagFinished, AsepriteTagLooped,
+        AsepriteTagStarted, AsepriteTilemapSprites, aseprite,
     };
 }
 
-/// Generates static tags and slices descriptions of an Aserpite animation.
+/// Generates static tags, slices and layers descriptions of an Aserpite animation.
 ///
 /// Calling it as follows:
 ///
 /// ```rust
 /// # use bevy_mod_aseprite::aseprite;
+/// # use bevy_mod_aseprite::info::AnimationDirection;
 /// #[allow(non_snake_case)]
 /// #[aseprite(file = "player.ase")]
 /// pub mod Player {}
+/// #
+/// # assert_eq!((Player::WIDTH, Player::HEIGHT), (120, 127));
+/// # assert_eq!(Player::FRAME_COUNT, 35);
+/// # assert_eq!(Player::FRAME_DURATIONS[..4], [130, 130, 130, 200]);
+/// # let total = Player::FRAME_DURATIONS.iter().map(|&d| d as u32).sum::<u32>();
+/// # assert_eq!(total, Player::DURATION_MS);
+/// # assert_eq!(Player::DURATION_MS, 4930);
+/// # assert_eq!(Player::tags::attack::RANGE, 22..=26);
+/// # assert!(matches!(Player::tags::attack::DIRECTION, AnimationDirection::Forward));
+/// # assert_eq!(Player::tags::attack::REPEAT, None);
+/// # assert_eq!(Player::tags::attack::DURATION_MS, 550);
 /// ```
 ///
 /// Will generate:
@@ -35,20 +75,161 @@ pub mod prelude {
 /// #[allow(non_snake_case)]
 /// pub mod Player {
 ///     pub const PATH: &'static str = "player.ase";
+///     pub const WIDTH: u16 = 120;
+///     pub const HEIGHT: u16 = 127;
+///     pub const FRAME_COUNT: usize = 35;
+///     pub const FRAME_DURATIONS: [u16; 35] = [
+///         130, 130, 130, 200, 200, 200, 200, 200, 200, 200, 200, 200, 200, 200, 100, 100, 100, 100,
+///         100, 100, 100, 100, 110, 110, 110, 110, 110, 120, 120, 120, 120, 120, 130, 130, 130,
+///     ];
+///     pub const DURATION_MS: u32 = 4930;
 ///     pub mod tags {
 ///         pub const WOUND:  ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("wound");
 ///         pub const STAND:  ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("stand");
 ///         pub const MOVE:   ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("move");
 ///         pub const ATTACK: ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("attack");
+///         pub mod attack {
+///             pub const RANGE: ::std::ops::RangeInclusive<u16> = 22..=26;
+///             pub const DIRECTION: ::bevy_mod_aseprite::info::AnimationDirection =
+///                 ::bevy_mod_aseprite::info::AnimationDirection::Forward;
+///             pub const REPEAT: ::std::option::Option<u16> = None;
+///             pub const DURATION_MS: u32 = 550;
+///         }
+///         // And a module for each tag
 ///         pub const DIE:    ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("die");
 ///         pub const JUMP:   ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("jump");
 ///         pub const FALL:   ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("fall");
 ///         pub const DASH:   ::bevy_mod_aseprite::AsepriteTag = ::bevy_mod_aseprite::AsepriteTag::new("dash");
 ///     }
 ///     pub mod slices {}
+///     pub mod layers {
+///         pub const ANIMS: ::bevy_mod_aseprite::AsepriteLayer = ::bevy_mod_aseprite::AsepriteLayer::new("anims");
+///     }
 /// }
 /// ```
 ///
+/// Tags and slices with a user data text also get a `USER_DATA` constant in their module:
+///
+/// ```rust
+/// # use bevy_mod_aseprite::prelude::*;
+/// #[allow(non_snake_case)]
+/// #[aseprite(file = "tests/user_data.aseprite")]
+/// pub mod Knight {}
+///
+/// assert_eq!(Knight::tags::hit::USER_DATA, "sfx/hit.ogg");
+/// assert_eq!(Knight::slices::hurtbox::USER_DATA, "damage=3");
+/// assert_eq!(Knight::layers::BODY, AsepriteLayer::new("body"));
+/// ```
+///
+/// Layers of different groups sharing a name get a single constant:
+///
+/// ```rust
+/// # use bevy_mod_aseprite::prelude::*;
+/// // The `front` and `back` groups both have a `hand` layer
+/// #[allow(non_snake_case)]
+/// #[aseprite(file = "tests/layer_groups.aseprite")]
+/// pub mod Rig {}
+///
+/// assert_eq!(Rig::layers::HAND, AsepriteLayer::new("hand"));
+/// assert_eq!(Rig::layers::FRONT, AsepriteLayer::new("front"));
+/// ```
+///
+/// Names are turned into valid identifiers, for instance the `1hit` tag gives `tags::_1HIT` and
+/// `tags::_1hit`. Names giving the same identifier, such as `hurt box` and `hurt_box`, are
+/// reported as errors.
+///
+/// The file is looked up in the `assets` directory of the crate calling the macro, next to its
+/// `Cargo.toml`. Another directory can be set with `asset_root`, for instance when
+/// `AssetPlugin::file_path` is customized:
+///
+/// ```rust,ignore
+/// #[aseprite(file = "player.ase", asset_root = "../game/assets")]
+/// pub mod Player {}
+/// ```
+///
+/// The crate is recompiled when the file changes.
+///
+/// Tag durations are the time of a single pass through their frames, without repetitions, which
+/// is handy for gameplay timings:
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use bevy_mod_aseprite::aseprite;
+/// # #[allow(non_snake_case)]
+/// # #[aseprite(file = "player.ase")]
+/// # pub mod Player {}
+/// const ATTACK_COOLDOWN: Duration = Duration::from_millis(Player::tags::attack::DURATION_MS as u64);
+/// # assert_eq!(ATTACK_COOLDOWN, Duration::from_millis(550));
+/// ```
+///
+/// With `enums`, `Tag` and `Slice` enums are also generated, with a variant per name. They can
+/// be matched exhaustively, iterated with `ALL`, parsed with `FromStr` and converted into
+/// [`AsepriteTag`] and [`AsepriteSlice`]:
+///
+/// ```rust
+/// # use bevy_mod_aseprite::prelude::*;
+/// #[allow(non_snake_case)]
+/// #[aseprite(file = "player.ase", enums)]
+/// pub mod Player {}
+///
+/// fn stand(info: &AsepriteInfo) -> AsepriteAnimation {
+///     AsepriteAnimation::new(info, Player::Tag::Stand)
+/// }
+///
+/// assert_eq!(
+///     Player::Tag::ALL,
+///     [
+///         Player::Tag::Wound,
+///         Player::Tag::Stand,
+///         Player::Tag::Move,
+///         Player::Tag::Attack,
+///         Player::Tag::Die,
+///         Player::Tag::Jump,
+///         Player::Tag::Fall,
+///         Player::Tag::Dash,
+///     ]
+/// );
+/// assert_eq!(Player::Tag::Attack.as_str(), "attack");
+/// assert_eq!(AsepriteTag::from(Player::Tag::Attack), Player::tags::ATTACK);
+/// for tag in Player::Tag::ALL {
+///     assert_eq!(tag.as_str().parse::<Player::Tag>(), Ok(tag));
+/// }
+/// assert_eq!(
+///     "run".parse::<Player::Tag>(),
+///     Err(Player::UnknownName("run".to_string()))
+/// );
+/// assert!(Player::Slice::ALL.is_empty());
+/// ```
+///
+/// With `dir`, a module is generated for each `.ase` and `.aseprite` file of a directory and its
+/// sub-directories, named after the file in snake case, along with the list of all their paths.
+/// Other files are skipped:
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_mod_aseprite::prelude::*;
+/// #[aseprite(dir = "tests/enemies")]
+/// pub mod enemies {}
+///
+/// fn load_enemies(asset_server: Res<AssetServer>) {
+///     let goblin: Handle<AsepriteAsset> = asset_server.load(enemies::goblin_king::PATH);
+///     let handles = enemies::PATHS.map(|path| asset_server.load::<AsepriteAsset>(path));
+/// }
+///
+/// assert_eq!(enemies::_2slime::tags::BOUNCE, AsepriteTag::new("bounce"));
+/// assert_eq!(enemies::boss_room::type_::PATH, "tests/enemies/Boss Room/type.ase");
+/// assert_eq!(
+///     enemies::PATHS,
+///     [
+///         "tests/enemies/2slime.aseprite",
+///         "tests/enemies/Boss Room/type.ase",
+///         "tests/enemies/Goblin-King.ase",
+///     ]
+/// );
+/// ```
+///
+/// Files added to the directory are only picked up when the crate is recompiled.
+///
 /// In bevy code it helps ensuring tags are up to date, as in this example:
 ///
 /// ```rust
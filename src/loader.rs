@@ -1,27 +1,197 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use aseprite_loader::binary::chunk::Chunk;
+use aseprite_loader::binary::chunks::cel::CelContent;
+use aseprite_loader::binary::chunks::layer::LayerFlags;
+use aseprite_loader::binary::chunks::user_data::{PropertiesMap, UserDataChunk, Value, Vector};
+use aseprite_loader::binary::color_depth::ColorDepth;
+use aseprite_loader::binary::file::parse_file;
+use aseprite_loader::binary::raw_file::{RawFile, parse_raw_file};
+use aseprite_loader::loader::{
+    AsepriteFile, Frame, FrameCel, LayerSelection, LoadImageError, LoadSpriteError, decompress,
+};
 use bevy::asset::uuid::Uuid;
 use bevy::asset::{AssetLoader, RenderAssetUsages};
+use bevy::image::ImageSampler;
 use bevy::log;
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
 use bevy::tasks::ConditionalSendFuture;
+use serde::{Deserialize, Serialize};
 
-use crate::anim::{AsepriteSlice, AsepriteTag};
-use crate::info::{AsepriteInfo, Palette};
-use crate::plugin::AsepriteAsset;
+use crate::anim::{AsepriteLayer, AsepriteSlice, AsepriteTag};
+use crate::info::{
+    AsepriteInfo, Layer, LayerType, Palette, SliceKey, Tag, UserData, UserProperty, layer_path,
+};
+use crate::plugin::{AsepriteAsset, AsepriteLayerAtlas};
+use crate::tilemap::{AsepriteTile, AsepriteTileGrid, AsepriteTilemap, AsepriteTileset};
 
 #[derive(Debug, Default, TypePath)]
 pub struct AsepriteLoader;
 
+/// Settings of the [`AsepriteLoader`], they can be set per asset in its `.meta` file or
+/// with [`AssetServer::load_with_settings`].
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+pub struct AsepriteLoaderSettings {
+    /// Padding in pixels added to the right and bottom edges of each frame in the atlas
+    pub padding: UVec2,
+    /// Whether the atlas texture is stored in sRGB or linear color space
+    pub is_srgb: bool,
+    /// Sampler used when rendering the atlas texture
+    #[reflect(ignore)]
+    pub sampler: ImageSampler,
+    /// Layers that are combined into each frame
+    pub layers: AsepriteLayers,
+    /// Additional atlases built for individual layers or groups
+    pub split_layers: AsepriteLayerSplit,
+    /// Frames that are loaded, other frames are discarded
+    pub frames: AsepriteFrames,
+    /// Whether frames are cropped to their non-transparent bounds in the atlas
+    ///
+    /// The bounds are stored in [`AsepriteInfo::frame_trims`] and the [`Anchor`] of the
+    /// sprite is updated so that it doesn't move relative to the canvas.
+    ///
+    /// [`Anchor`]: bevy::sprite::Anchor
+    pub trim: bool,
+}
+
+impl Default for AsepriteLoaderSettings {
+    fn default() -> Self {
+        Self {
+            padding: UVec2::ZERO,
+            is_srgb: true,
+            sampler: ImageSampler::Default,
+            layers: AsepriteLayers::default(),
+            split_layers: AsepriteLayerSplit::default(),
+            frames: AsepriteFrames::default(),
+            trim: false,
+        }
+    }
+}
+
+/// Selection of the layers combined into the atlas frames
+///
+/// Naming a group selects all the layers it contains.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+pub enum AsepriteLayers {
+    /// Layers that are visible in the Aseprite file
+    #[default]
+    Visible,
+    /// All layers, regardless of their visibility in the Aseprite file
+    All,
+    /// Only layers with the given names
+    Include(Vec<String>),
+    /// Visible layers except the ones with the given names
+    Exclude(Vec<String>),
+}
+
+impl AsepriteLayers {
+    fn mask(&self, layers: &[Layer]) -> Vec<bool> {
+        let named = |index: usize, names: &[String]| {
+            ancestors(layers, index).any(|layer| names.iter().any(|n| layer.name.as_ref() == n))
+        };
+        (0..layers.len())
+            .map(|index| match self {
+                Self::Visible => layers[index].visible,
+                Self::All => true,
+                Self::Include(names) => named(index, names),
+                Self::Exclude(names) => layers[index].visible && !named(index, names),
+            })
+            .collect()
+    }
+}
+
+/// Splitting of the selected layers into separate atlases
+///
+/// Each atlas is available in [`AsepriteAsset::layers`] and as labeled sub-assets
+/// `layers/{path}/atlas_layout` and `layers/{path}/atlas_texture`. The path of a layer joins the
+/// names of its parent groups and its own with `/`, such as `back/hand`, so layers sharing a
+/// name in different groups get their own atlas.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+pub enum AsepriteLayerSplit {
+    /// Only the combined atlas is built
+    #[default]
+    None,
+    /// One atlas per layer, groups are not part of the split
+    Layers,
+    /// One atlas per top level layer or group, a group combines all the layers it contains
+    Groups,
+}
+
+impl AsepriteLayerSplit {
+    fn masks(&self, layers: &[Layer], mask: &[bool]) -> Vec<(AsepriteLayer, Vec<bool>)> {
+        let roots = layers.iter().filter(|layer| match self {
+            Self::None => false,
+            Self::Layers => !layer.is_group(),
+            Self::Groups => layer.parent.is_none(),
+        });
+        roots
+            .map(|root| {
+                let split_mask = (0..layers.len())
+                    .map(|index| {
+                        mask[index] && ancestors(layers, index).any(|l| std::ptr::eq(l, root))
+                    })
+                    .collect::<Vec<_>>();
+                (layer_path(layers, root), split_mask)
+            })
+            .filter(|(_, split_mask)| split_mask.contains(&true))
+            .collect()
+    }
+}
+
+/// Iterates over a layer and its parent groups
+fn ancestors(layers: &[Layer], index: usize) -> impl Iterator<Item = &Layer> {
+    std::iter::successors(layers.get(index), |layer| {
+        layer.parent.and_then(|parent| layers.get(parent))
+    })
+}
+
+/// Selection of the frames loaded from the Aseprite file
+///
+/// Loaded frames are renumbered from 0, tags and slices are adjusted accordingly.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+pub enum AsepriteFrames {
+    /// All frames
+    #[default]
+    All,
+    /// Frames within the inclusive range
+    Range { start: u16, end: u16 },
+    /// Frames covered by the tag with the given name
+    Tag(String),
+}
+
+impl AsepriteFrames {
+    fn range(&self, raw: &AsepriteFile) -> Result<RangeInclusive<usize>, AsepriteLoaderError> {
+        let frame_count = raw.frames().len();
+        let (start, end) = match self {
+            Self::All => return Ok(0..=frame_count.saturating_sub(1)),
+            Self::Range { start, end } => (*start, *end),
+            Self::Tag(name) => {
+                let Some(tag) = raw.tags().iter().find(|tag| &tag.name == name) else {
+                    return Err(AsepriteLoaderError::MissingTag(name.clone()));
+                };
+                (*tag.range.start(), *tag.range.end())
+            }
+        };
+        if start > end || end as usize >= frame_count {
+            return Err(AsepriteLoaderError::InvalidFrameRange(start..=end));
+        }
+        Ok(start as usize..=end as usize)
+    }
+}
+
 impl AssetLoader for AsepriteLoader {
     type Asset = AsepriteAsset;
-    type Settings = ();
+    type Settings = AsepriteLoaderSettings;
     type Error = AsepriteLoaderError;
 
     fn load(
         &self,
         reader: &mut dyn bevy::asset::io::Reader,
-        _settings: &Self::Settings,
+        settings: &Self::Settings,
         load_context: &mut bevy::asset::LoadContext,
     ) -> impl ConditionalSendFuture<Output = Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
@@ -29,57 +199,74 @@ impl AssetLoader for AsepriteLoader {
 
             let mut bytes = vec![];
             reader.read_to_end(&mut bytes).await?;
-            let raw = aseprite_loader::loader::AsepriteFile::load(&bytes)?;
-
-            let mut images = Vec::new();
-            for (index, _frame) in raw.frames().iter().enumerate() {
-                let (width, height) = raw.size();
-                let mut buffer = vec![0; width as usize * height as usize * 4];
-                let _hash = raw.combined_frame_image(index, buffer.as_mut_slice())?;
-                let image = Image::new_fill(
-                    Extent3d {
-                        width: width as u32,
-                        height: height as u32,
-                        depth_or_array_layers: 1,
-                    },
-                    TextureDimension::D2,
-                    &buffer,
-                    TextureFormat::Rgba8UnormSrgb,
-                    RenderAssetUsages::default(),
-                );
-                images.push(image);
-            }
+            let raw = load_file(&bytes)?;
+
+            let frame_range = settings.frames.range(&raw)?;
+            let mut layers = layers_info(&raw);
+            let mask = settings.layers.mask(&layers);
+            let (atlas_frames, frame_atlas_indices) = unique_frames(&raw, &frame_range, &mask);
+
+            let selection = LayerSelection::Mask(mask.clone());
+            let atlas_trims = if settings.trim {
+                trim_frames(&raw, &atlas_frames, &selection)?
+            } else {
+                Vec::new()
+            };
 
             // Atlas
 
-            let mut atlas_builder = TextureAtlasBuilder::default();
-            let mut frame_images = Vec::new();
-            for image in images.iter() {
-                let handle_id = AssetId::Uuid {
-                    uuid: Uuid::new_v4(),
-                };
-                frame_images.push(handle_id);
-                atlas_builder.add_texture(Some(handle_id), image);
-            }
-            let (layout, _source, image) = atlas_builder.build()?;
+            let (layout, image) =
+                build_atlas(&raw, &atlas_frames, &selection, &atlas_trims, settings)?;
             let atlas_layout = load_context.add_labeled_asset("atlas_layout".into(), layout);
             let atlas_texture = load_context.add_labeled_asset("atlas_texture".into(), image);
 
+            let mut layer_atlases = HashMap::new();
+            for (layer, split_mask) in settings.split_layers.masks(&layers, &mask) {
+                if layer_atlases.contains_key(&layer) {
+                    log::warn!("Duplicate layer {layer:?}: skipping its atlas");
+                    continue;
+                }
+                let (layout, image) = build_atlas(
+                    &raw,
+                    &atlas_frames,
+                    &LayerSelection::Mask(split_mask),
+                    &atlas_trims,
+                    settings,
+                )?;
+                let path = layer.as_ref();
+                let atlas_layout =
+                    load_context.add_labeled_asset(format!("layers/{path}/atlas_layout"), layout);
+                let atlas_texture =
+                    load_context.add_labeled_asset(format!("layers/{path}/atlas_texture"), image);
+                layer_atlases.insert(
+                    layer,
+                    AsepriteLayerAtlas {
+                        atlas_layout,
+                        atlas_texture,
+                    },
+                );
+            }
+
             // Information
 
             let dimensions = raw.size();
 
             let mut tags = HashMap::new();
             raw.tags().iter().for_each(|tag| {
-                tags.insert(AsepriteTag::from(tag.name.as_str()), tag.clone());
+                if let Some(tag) = clip_tag(tag, &frame_range) {
+                    tags.insert(AsepriteTag::from(tag.name.as_str()), tag);
+                }
             });
 
             let mut slices = HashMap::new();
             raw.slices().iter().for_each(|slice| {
-                slices.insert(AsepriteSlice::from(slice.name), slice.slice_keys.clone());
+                let slice_keys = clip_slice_keys(&slice.slice_keys, &frame_range);
+                if !slice_keys.is_empty() {
+                    slices.insert(AsepriteSlice::from(slice.name), slice_keys);
+                }
             });
 
-            let frame_count = raw.frames().iter().count();
+            let frame_count = frame_range.clone().count();
 
             let palette = raw
                 .file
@@ -87,28 +274,99 @@ impl AssetLoader for AsepriteLoader {
                 .as_ref()
                 .map(|p| Palette { colors: p.colors });
 
+            // Older files store 0 for 256 colors
+            let palette_size = match raw.file.header.color_count {
+                0 => 256,
+                count => (count as usize).min(256),
+            };
             let transparent_palette = raw.file.header.transparent_index;
 
-            let frame_durations = raw
-                .frames()
+            let frame_durations = raw.frames()[frame_range.clone()]
                 .iter()
                 .map(|frame| frame.duration)
                 .collect::<Vec<_>>();
 
+            // Chunks skipped by the high level loader
+            let raw_file = parse_raw_file(&bytes).map_err(|err| LoadSpriteError::Parse {
+                message: err.to_string(),
+            })?;
+
+            let mut user_data = FileUserData::parse(&raw_file, &frame_range);
+            for (layer, layer_user_data) in layers.iter_mut().zip(user_data.layers.drain(..)) {
+                layer.user_data = layer_user_data;
+            }
+            user_data.tags.retain(|tag, _| tags.contains_key(tag));
+            user_data
+                .slices
+                .retain(|slice, _| slices.contains_key(slice));
+
+            // Tiles
+
+            let file_tiles = FileTiles::parse(&raw_file, raw.file.palette.as_ref(), &frame_range)?;
+            let tilesets = file_tiles
+                .tilesets
+                .into_iter()
+                .enumerate()
+                .map(|(index, (name, tile_size, layout, mut image))| {
+                    image.sampler = settings.sampler.clone();
+                    if !settings.is_srgb {
+                        image.texture_descriptor.format = TextureFormat::Rgba8Unorm;
+                    }
+                    AsepriteTileset {
+                        name,
+                        tile_size,
+                        atlas_layout: load_context
+                            .add_labeled_asset(format!("tilesets/{index}/atlas_layout"), layout),
+                        atlas_texture: load_context
+                            .add_labeled_asset(format!("tilesets/{index}/atlas_texture"), image),
+                    }
+                })
+                .collect();
+            let mut tilemaps = HashMap::new();
+            for tilemap in file_tiles.tilemaps {
+                if tilemaps.contains_key(&tilemap.layer) {
+                    log::warn!("Duplicate layer {:?}: skipping its tilemap", tilemap.layer);
+                    continue;
+                }
+                let name = format!("tilemaps/{}", tilemap.layer.as_ref());
+                tilemaps.insert(
+                    tilemap.layer.clone(),
+                    load_context.add_labeled_asset(name, tilemap),
+                );
+            }
+
             let info = AsepriteInfo {
                 dimensions,
                 tags,
                 slices,
+                layers,
                 frame_count,
                 palette,
+                palette_size,
                 transparent_palette,
                 frame_durations,
+                frame_trims: if atlas_trims.is_empty() {
+                    Vec::new()
+                } else {
+                    frame_atlas_indices
+                        .iter()
+                        .map(|&i| atlas_trims[i])
+                        .collect()
+                },
+                frame_atlas_indices,
+                user_data: user_data.sprite,
+                tag_user_data: user_data.tags,
+                slice_user_data: user_data.slices,
+                cel_user_data: user_data.cels,
             };
 
             Ok(AsepriteAsset {
                 info,
                 atlas_texture,
                 atlas_layout,
+                layers: layer_atlases,
+                tilesets,
+                tilemaps,
             })
         })
     }
@@ -118,6 +376,673 @@ impl AssetLoader for AsepriteLoader {
     }
 }
 
+/// Loads the file as [`AsepriteFile::load`] does, which fails on tilemap cels
+///
+/// Only normal layers and groups are kept, the cels of other layers are skipped and the
+/// remaining ones refer to their layer's index in [`AsepriteFile::layers`].
+fn load_file(bytes: &[u8]) -> Result<AsepriteFile<'_>, LoadSpriteError> {
+    let file = parse_file(bytes).map_err(|err| LoadSpriteError::Parse {
+        message: err.to_string(),
+    })?;
+
+    let mut layers = Vec::new();
+    let layer_indices = file
+        .layers
+        .iter()
+        .map(|layer| {
+            matches!(layer.layer_type, LayerType::Normal | LayerType::Group).then(|| {
+                layers.push(aseprite_loader::loader::Layer {
+                    name: layer.name.to_string(),
+                    opacity: layer.opacity,
+                    blend_mode: layer.blend_mode,
+                    visible: layer.flags.contains(LayerFlags::VISIBLE),
+                    layer_type: layer.layer_type,
+                });
+                layers.len() - 1
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Images by frame and raw layer index, linked cels may refer to any frame
+    let mut images = Vec::new();
+    let mut image_indices = HashMap::new();
+    for (index, frame) in file.frames.iter().enumerate() {
+        for cel in frame.cels.iter().flatten() {
+            if let CelContent::Image(image) = &cel.content {
+                image_indices.insert((index, cel.layer_index as usize), images.len());
+                images.push(image.clone());
+            }
+        }
+    }
+
+    let mut frames = Vec::with_capacity(file.frames.len());
+    for (index, frame) in file.frames.iter().enumerate() {
+        let mut cels = Vec::new();
+        for cel in frame.cels.iter().flatten() {
+            let raw_layer = cel.layer_index as usize;
+            let Some(layer_index) = layer_indices.get(raw_layer).copied().flatten() else {
+                continue;
+            };
+            let image_index = match &cel.content {
+                CelContent::Image(_) => image_indices[&(index, raw_layer)],
+                CelContent::LinkedCel { frame_position } => *image_indices
+                    .get(&(*frame_position as usize, raw_layer))
+                    .ok_or_else(|| LoadSpriteError::Parse {
+                        message: format!("invalid linked cel at frame {index} layer {raw_layer}"),
+                    })?,
+                _ => {
+                    return Err(LoadSpriteError::Parse {
+                        message: format!("invalid cel at frame {index} layer {raw_layer}"),
+                    });
+                }
+            };
+            let image = &images[image_index];
+            cels.push(FrameCel {
+                origin: (cel.x, cel.y),
+                size: (image.width, image.height),
+                layer_index,
+                image_index,
+            });
+        }
+        frames.push(Frame {
+            duration: frame.duration,
+            origin: (0, 0),
+            cels,
+        });
+    }
+
+    let tags = file
+        .tags
+        .iter()
+        .map(|tag| Tag {
+            name: tag.name.to_string(),
+            range: tag.frames.clone(),
+            direction: tag.animation_direction,
+            repeat: (tag.animation_repeat > 0).then_some(tag.animation_repeat),
+        })
+        .collect();
+
+    Ok(AsepriteFile {
+        file,
+        layers,
+        frames,
+        tags,
+        images,
+    })
+}
+
+/// Finds the frames in range that differ, considering the selected layers only
+///
+/// Returns the frames to put in the atlas, and the atlas index of each frame in range.
+fn unique_frames(
+    raw: &AsepriteFile,
+    frame_range: &RangeInclusive<usize>,
+    mask: &[bool],
+) -> (Vec<usize>, Vec<usize>) {
+    let mut atlas_frames = Vec::new();
+    let mut frame_atlas_indices = Vec::new();
+    let mut atlas_indices = HashMap::new();
+    for index in frame_range.clone() {
+        let mut hasher = DefaultHasher::new();
+        for cel in raw.frames()[index].cels.iter() {
+            if mask.get(cel.layer_index).copied().unwrap_or(false) {
+                (cel.image_index, cel.layer_index, cel.origin, cel.size).hash(&mut hasher);
+            }
+        }
+        let atlas_index = *atlas_indices.entry(hasher.finish()).or_insert_with(|| {
+            atlas_frames.push(index);
+            atlas_frames.len() - 1
+        });
+        frame_atlas_indices.push(atlas_index);
+    }
+    (atlas_frames, frame_atlas_indices)
+}
+
+/// Finds the non-transparent bounds of the given frames, combining the selected layers
+///
+/// Blank frames are reduced to their top left pixel.
+fn trim_frames(
+    raw: &AsepriteFile,
+    frames: &[usize],
+    layers: &LayerSelection,
+) -> Result<Vec<URect>, AsepriteLoaderError> {
+    let (width, height) = raw.size();
+    let mut trims = Vec::with_capacity(frames.len());
+    for &index in frames {
+        let mut buffer = vec![0; width as usize * height as usize * 4];
+        raw.render_frame(index, buffer.as_mut_slice(), layers)?;
+        let opaque = buffer
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[3] != 0)
+            .map(|(i, _)| UVec2::new(i as u32 % width as u32, i as u32 / width as u32));
+        let trim = opaque
+            .fold(None, |rect: Option<URect>, pixel| {
+                Some(match rect {
+                    Some(rect) => rect.union_point(pixel),
+                    None => URect::from_corners(pixel, pixel),
+                })
+            })
+            .map(|rect| URect::from_corners(rect.min, rect.max + UVec2::ONE))
+            .unwrap_or(URect::new(0, 0, 1, 1));
+        trims.push(trim);
+    }
+    Ok(trims)
+}
+
+/// Builds the atlas of the given frames, combining the selected layers
+///
+/// Frames are cropped to their trim rect, if any.
+fn build_atlas(
+    raw: &AsepriteFile,
+    frames: &[usize],
+    layers: &LayerSelection,
+    trims: &[URect],
+    settings: &AsepriteLoaderSettings,
+) -> Result<(TextureAtlasLayout, Image), AsepriteLoaderError> {
+    let format = if settings.is_srgb {
+        TextureFormat::Rgba8UnormSrgb
+    } else {
+        TextureFormat::Rgba8Unorm
+    };
+
+    let mut images = Vec::new();
+    for (i, &index) in frames.iter().enumerate() {
+        let (width, height) = raw.size();
+        let mut buffer = vec![0; width as usize * height as usize * 4];
+        raw.render_frame(index, buffer.as_mut_slice(), layers)?;
+        let rect = trims
+            .get(i)
+            .copied()
+            .unwrap_or(URect::new(0, 0, width as u32, height as u32));
+        if rect.size() != UVec2::new(width as u32, height as u32) {
+            buffer = (rect.min.y..rect.max.y)
+                .flat_map(|y| {
+                    let row = (y * width as u32) as usize;
+                    let start = (row + rect.min.x as usize) * 4;
+                    let end = (row + rect.max.x as usize) * 4;
+                    buffer[start..end].iter().copied()
+                })
+                .collect();
+        }
+        let image = Image::new_fill(
+            Extent3d {
+                width: rect.width(),
+                height: rect.height(),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &buffer,
+            format,
+            RenderAssetUsages::default(),
+        );
+        images.push(image);
+    }
+
+    let mut atlas_builder = TextureAtlasBuilder::default();
+    atlas_builder.format(format).padding(settings.padding);
+    let mut frame_images = Vec::new();
+    for image in images.iter() {
+        let handle_id = AssetId::Uuid {
+            uuid: Uuid::new_v4(),
+        };
+        frame_images.push(handle_id);
+        atlas_builder.add_texture(Some(handle_id), image);
+    }
+    let (layout, _source, mut image) = atlas_builder.build()?;
+    image.sampler = settings.sampler.clone();
+
+    Ok((layout, image))
+}
+
+/// Collects the layers with their parent group
+fn layers_info(raw: &AsepriteFile) -> Vec<Layer> {
+    let child_levels = raw
+        .file
+        .layers
+        .iter()
+        .filter(|layer| matches!(layer.layer_type, LayerType::Normal | LayerType::Group))
+        .map(|layer| layer.child_level as usize);
+
+    let mut groups: Vec<usize> = Vec::new();
+    raw.layers()
+        .iter()
+        .zip(child_levels)
+        .enumerate()
+        .map(|(index, (layer, child_level))| {
+            groups.truncate(child_level);
+            let parent = child_level
+                .checked_sub(1)
+                .and_then(|level| groups.get(level).copied());
+            if layer.layer_type == LayerType::Group {
+                groups.push(index);
+            }
+            Layer {
+                name: AsepriteLayer::from(layer.name.as_str()),
+                opacity: layer.opacity,
+                blend_mode: layer.blend_mode,
+                visible: layer.visible,
+                layer_type: layer.layer_type,
+                parent,
+                user_data: UserData::default(),
+            }
+        })
+        .collect()
+}
+
+/// Shifts a tag into the loaded frame range, returns `None` if it lies outside of it
+fn clip_tag(tag: &Tag, frame_range: &RangeInclusive<usize>) -> Option<Tag> {
+    let start = (*tag.range.start() as usize).max(*frame_range.start());
+    let end = (*tag.range.end() as usize).min(*frame_range.end());
+    if start > end {
+        return None;
+    }
+    let offset = *frame_range.start();
+    Some(Tag {
+        range: (start - offset) as u16..=(end - offset) as u16,
+        ..tag.clone()
+    })
+}
+
+/// Shifts slice keys into the loaded frame range
+///
+/// A key stays valid until the next one, so the last key preceding the range is kept
+/// and moved to the first loaded frame.
+fn clip_slice_keys(keys: &[SliceKey], frame_range: &RangeInclusive<usize>) -> Vec<SliceKey> {
+    let mut keys = keys.to_vec();
+    keys.sort_by_key(|key| key.frame_number);
+
+    let offset = *frame_range.start();
+    let mut clipped = Vec::with_capacity(keys.len());
+    for key in keys {
+        let frame = key.frame_number as usize;
+        if frame > *frame_range.end() {
+            break;
+        }
+        if frame <= offset {
+            clipped.clear();
+        }
+        clipped.push(SliceKey {
+            frame_number: frame.saturating_sub(offset) as u32,
+            ..key
+        });
+    }
+    clipped
+}
+
+/// User data of the file, the high level loader of aseprite-loader skips it
+#[derive(Default)]
+struct FileUserData {
+    sprite: UserData,
+    layers: Vec<UserData>, // Same order as the layers of `AsepriteInfo`
+    tags: HashMap<AsepriteTag, UserData>,
+    slices: HashMap<AsepriteSlice, UserData>,
+    cels: HashMap<(usize, usize), UserData>,
+}
+
+/// The element a user data chunk refers to, which is the chunk preceding it
+enum UserDataTarget {
+    None,
+    Sprite,
+    Layer(Option<usize>),
+    Cel(usize, usize),
+    Tags(std::vec::IntoIter<AsepriteTag>),
+    Slice(AsepriteSlice),
+}
+
+impl FileUserData {
+    fn parse(raw: &RawFile, frame_range: &RangeInclusive<usize>) -> Self {
+        let mut user_data = Self::default();
+        // Only normal layers and groups are kept, others are mapped to `None`
+        let mut layer_indices = Vec::new();
+        for (frame, raw_frame) in raw.frames.iter().enumerate() {
+            let mut target = if frame == 0 {
+                UserDataTarget::Sprite
+            } else {
+                UserDataTarget::None
+            };
+            for chunk in raw_frame.chunks.iter() {
+                target = match chunk {
+                    Chunk::Layer(layer) => {
+                        let index =
+                            matches!(layer.layer_type, LayerType::Normal | LayerType::Group).then(
+                                || {
+                                    user_data.layers.push(UserData::default());
+                                    user_data.layers.len() - 1
+                                },
+                            );
+                        layer_indices.push(index);
+                        UserDataTarget::Layer(index)
+                    }
+                    Chunk::Cel(cel) => {
+                        match layer_indices
+                            .get(cel.layer_index as usize)
+                            .copied()
+                            .flatten()
+                        {
+                            Some(layer) if frame_range.contains(&frame) => {
+                                UserDataTarget::Cel(frame - frame_range.start(), layer)
+                            }
+                            _ => UserDataTarget::None,
+                        }
+                    }
+                    Chunk::Tags(tags) => UserDataTarget::Tags(
+                        tags.tags
+                            .iter()
+                            .map(|tag| AsepriteTag::from(tag.name))
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ),
+                    Chunk::Slice(slice) => UserDataTarget::Slice(AsepriteSlice::from(slice.name)),
+                    Chunk::UserData(chunk) => {
+                        let data = user_data_from_chunk(chunk);
+                        match target {
+                            UserDataTarget::Sprite => user_data.sprite = data,
+                            UserDataTarget::Layer(Some(index)) => user_data.layers[index] = data,
+                            UserDataTarget::Cel(frame, layer) if !data.is_empty() => {
+                                user_data.cels.insert((frame, layer), data);
+                            }
+                            UserDataTarget::Tags(mut tags) => {
+                                if let Some(tag) = tags.next()
+                                    && !data.is_empty()
+                                {
+                                    user_data.tags.insert(tag, data);
+                                }
+                                target = UserDataTarget::Tags(tags);
+                                continue;
+                            }
+                            UserDataTarget::Slice(slice) if !data.is_empty() => {
+                                user_data.slices.insert(slice, data);
+                            }
+                            _ => (),
+                        }
+                        UserDataTarget::None
+                    }
+                    // Extra data of a cel comes before its user data
+                    Chunk::CelExtra(_) => target,
+                    // The sprite's user data follows its color profile and palettes
+                    Chunk::ColorProfile(_)
+                    | Chunk::Palette(_)
+                    | Chunk::Palette0004(_)
+                    | Chunk::Palette0011(_)
+                        if matches!(target, UserDataTarget::Sprite) =>
+                    {
+                        target
+                    }
+                    _ => UserDataTarget::None,
+                };
+            }
+        }
+        user_data
+    }
+}
+
+/// Tilesets and tilemap layers of the file, the high level loader of aseprite-loader skips them
+struct FileTiles {
+    tilesets: Vec<(String, UVec2, TextureAtlasLayout, Image)>,
+    tilemaps: Vec<AsepriteTilemap>,
+}
+
+impl FileTiles {
+    fn parse(
+        raw: &RawFile,
+        palette: Option<&Palette>,
+        frame_range: &RangeInclusive<usize>,
+    ) -> Result<Self, AsepriteLoaderError> {
+        let mut tilesets = Vec::new();
+        let mut tileset_indices = HashMap::new();
+        // Raw layer index, name and tileset id of tilemap layers
+        let mut tilemap_layers = Vec::new();
+        let mut layer_count = 0;
+        let mut grids = HashMap::new();
+        for (frame, raw_frame) in raw.frames.iter().enumerate() {
+            for chunk in raw_frame.chunks.iter() {
+                match chunk {
+                    Chunk::Layer(layer) => {
+                        if let (LayerType::Tilemap, Some(tileset)) =
+                            (layer.layer_type, layer.tileset_index)
+                        {
+                            tilemap_layers.push((layer_count, layer.name, tileset));
+                        }
+                        layer_count += 1;
+                    }
+                    Chunk::Tileset(tileset) => {
+                        let Some(tiles) = &tileset.tiles else {
+                            log::warn!("Tileset {:?} is external: skipping it", tileset.name);
+                            continue;
+                        };
+                        let tile_size = UVec2::new(tileset.width as u32, tileset.height as u32);
+                        let count = tileset.number_of_tiles;
+                        let size = Extent3d {
+                            width: tile_size.x,
+                            height: tile_size.y * count,
+                            depth_or_array_layers: 1,
+                        };
+                        let pixels = (size.width * size.height) as usize;
+                        let data =
+                            rgba_pixels(raw.header.color_depth, palette, tiles.data, pixels)?;
+                        let image = Image::new(
+                            size,
+                            TextureDimension::D2,
+                            data,
+                            TextureFormat::Rgba8UnormSrgb,
+                            RenderAssetUsages::default(),
+                        );
+                        let layout = TextureAtlasLayout::from_grid(tile_size, 1, count, None, None);
+                        tileset_indices.insert(tileset.id, tilesets.len());
+                        tilesets.push((tileset.name.to_string(), tile_size, layout, image));
+                    }
+                    Chunk::Cel(cel) => {
+                        let layer = cel.layer_index as usize;
+                        if !tilemap_layers.iter().any(|(index, ..)| *index == layer) {
+                            continue;
+                        }
+                        let grid = match &cel.content {
+                            CelContent::LinkedCel { frame_position } => {
+                                grids.get(&(layer, *frame_position as usize)).cloned()
+                            }
+                            CelContent::CompressedTilemap {
+                                width,
+                                height,
+                                bits_per_tile,
+                                bitmask_tile_id,
+                                // aseprite-loader reads the X and Y flip masks in reverse order
+                                bitmask_x_flip: bitmask_y_flip,
+                                bitmask_y_flip: bitmask_x_flip,
+                                bitmask_diagonal_flip,
+                                data,
+                            } => {
+                                let bytes_per_tile = (*bits_per_tile / 8) as usize;
+                                let mut buffer =
+                                    vec![0; *width as usize * *height as usize * bytes_per_tile];
+                                decompress(data, &mut buffer)?;
+                                let tiles = buffer
+                                    .chunks_exact(bytes_per_tile)
+                                    .map(|bytes| {
+                                        let mut value = [0; 4];
+                                        value[..bytes.len().min(4)]
+                                            .copy_from_slice(&bytes[..bytes.len().min(4)]);
+                                        let value = u32::from_le_bytes(value);
+                                        AsepriteTile {
+                                            index: value & bitmask_tile_id,
+                                            flip_x: value & bitmask_x_flip != 0,
+                                            flip_y: value & bitmask_y_flip != 0,
+                                            flip_diagonal: value & bitmask_diagonal_flip != 0,
+                                        }
+                                    })
+                                    .collect();
+                                Some(AsepriteTileGrid {
+                                    offset: IVec2::new(cel.x as i32, cel.y as i32),
+                                    size: UVec2::new(*width as u32, *height as u32),
+                                    tiles,
+                                })
+                            }
+                            _ => None,
+                        };
+                        if let Some(grid) = grid {
+                            grids.insert((layer, frame), grid);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let tilemaps = tilemap_layers
+            .into_iter()
+            .filter_map(|(layer, name, tileset_id)| {
+                let Some(&tileset) = tileset_indices.get(&tileset_id) else {
+                    log::warn!("Tilemap layer {name:?} has no tileset: skipping it");
+                    return None;
+                };
+                Some(AsepriteTilemap {
+                    layer: AsepriteLayer::from(name),
+                    tileset,
+                    tile_size: tilesets[tileset].1,
+                    frames: frame_range
+                        .clone()
+                        .map(|frame| grids.get(&(layer, frame)).cloned())
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Ok(Self { tilesets, tilemaps })
+    }
+}
+
+/// Decompresses pixels of the file's color depth into RGBA
+fn rgba_pixels(
+    color_depth: ColorDepth,
+    palette: Option<&Palette>,
+    data: &[u8],
+    pixels: usize,
+) -> Result<Vec<u8>, AsepriteLoaderError> {
+    let mut buffer = vec![0; pixels * color_depth.bpp() as usize / 8];
+    decompress(data, &mut buffer)?;
+    match color_depth {
+        ColorDepth::Rgba => Ok(buffer),
+        ColorDepth::Grayscale => Ok(buffer
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect()),
+        ColorDepth::Indexed => {
+            let palette = palette.ok_or(LoadImageError::MissingPalette)?;
+            Ok(buffer
+                .iter()
+                .flat_map(|&index| {
+                    let color = palette.colors[index as usize];
+                    [color.red, color.green, color.blue, color.alpha]
+                })
+                .collect())
+        }
+        ColorDepth::Unknown(_) => Err(LoadImageError::UnsupportedColorDepth.into()),
+    }
+}
+
+fn user_data_from_chunk(chunk: &UserDataChunk) -> UserData {
+    let mut properties = HashMap::new();
+    match &chunk.properties_maps {
+        Some(Ok((_, maps))) => {
+            // Properties of extensions use other ids
+            for map in maps.iter().filter(|map| map.extension_entry_id == 0) {
+                properties.extend(properties_from_map(map));
+            }
+        }
+        Some(Err(err)) => log::warn!("Invalid user data properties: {err}"),
+        None => (),
+    }
+    UserData {
+        text: chunk.text.map(str::to_string),
+        color: chunk
+            .color
+            .map(|c| Color::srgba_u8(c.red, c.green, c.blue, c.alpha)),
+        properties,
+    }
+}
+
+fn properties_from_map(map: &PropertiesMap) -> HashMap<String, UserProperty> {
+    map.properties
+        .iter()
+        .map(|property| {
+            (
+                property.name.to_string(),
+                property_from_value(&property.value),
+            )
+        })
+        .collect()
+}
+
+fn property_from_value(value: &Value) -> UserProperty {
+    match value {
+        Value::Bool(v) => UserProperty::Bool(*v),
+        Value::Int8(v) => UserProperty::Int(*v as i64),
+        Value::Uint8(v) => UserProperty::Uint(*v as u64),
+        Value::Int16(v) => UserProperty::Int(*v as i64),
+        Value::Uint16(v) => UserProperty::Uint(*v as u64),
+        Value::Int32(v) => UserProperty::Int(*v as i64),
+        Value::Uint32(v) => UserProperty::Uint(*v as u64),
+        Value::Int64(v) => UserProperty::Int(*v),
+        Value::Uint64(v) => UserProperty::Uint(*v),
+        Value::Fixed(v) => UserProperty::Float(fixed_to_f64(v)),
+        Value::Float(v) => UserProperty::Float(*v as f64),
+        Value::Double(v) => UserProperty::Float(*v),
+        Value::String(v) => UserProperty::String(v.to_string()),
+        Value::Point(v) => UserProperty::Point(IVec2::new(v.x, v.y)),
+        Value::Size(v) => UserProperty::Size(IVec2::new(v.width, v.height)),
+        Value::Rect(v) => UserProperty::Rect(IRect::new(
+            v.point.x,
+            v.point.y,
+            v.point.x + v.size.width,
+            v.point.y + v.size.height,
+        )),
+        Value::Vector(v) => UserProperty::Vector(properties_from_vector(v)),
+        Value::MixedVector(v) => UserProperty::Vector(v.iter().map(property_from_value).collect()),
+        Value::PropertiesMap(v) => UserProperty::Map(properties_from_map(v)),
+        Value::Uuid(v) => UserProperty::Uuid(*v),
+    }
+}
+
+fn properties_from_vector(vector: &Vector) -> Vec<UserProperty> {
+    fn map<T>(values: &[T], f: impl Fn(&T) -> UserProperty) -> Vec<UserProperty> {
+        values.iter().map(f).collect()
+    }
+    match vector {
+        Vector::Mixed(v) => map(v, property_from_value),
+        Vector::Bool(v) => map(v, |v| UserProperty::Bool(*v)),
+        Vector::Int8(v) => map(v, |v| UserProperty::Int(*v as i64)),
+        Vector::Uint8(v) => map(v, |v| UserProperty::Uint(*v as u64)),
+        Vector::Int16(v) => map(v, |v| UserProperty::Int(*v as i64)),
+        Vector::Uint16(v) => map(v, |v| UserProperty::Uint(*v as u64)),
+        Vector::Int32(v) => map(v, |v| UserProperty::Int(*v as i64)),
+        Vector::Uint32(v) => map(v, |v| UserProperty::Uint(*v as u64)),
+        Vector::Int64(v) => map(v, |v| UserProperty::Int(*v)),
+        Vector::Uint64(v) => map(v, |v| UserProperty::Uint(*v)),
+        Vector::Fixed(v) => map(v, |v| UserProperty::Float(fixed_to_f64(v))),
+        Vector::Float(v) => map(v, |v| UserProperty::Float(*v as f64)),
+        Vector::Double(v) => map(v, |v| UserProperty::Float(*v)),
+        Vector::String(v) => map(v, |v| UserProperty::String(v.to_string())),
+        Vector::Point(v) => map(v, |v| UserProperty::Point(IVec2::new(v.x, v.y))),
+        Vector::Size(v) => map(v, |v| UserProperty::Size(IVec2::new(v.width, v.height))),
+        Vector::Rect(v) => map(v, |v| {
+            UserProperty::Rect(IRect::new(
+                v.point.x,
+                v.point.y,
+                v.point.x + v.size.width,
+                v.point.y + v.size.height,
+            ))
+        }),
+        Vector::Vector(v) => map(v, |v| UserProperty::Vector(properties_from_vector(v))),
+        Vector::PropertiesMap(v) => map(v, |v| UserProperty::Map(properties_from_map(v))),
+        Vector::Uuid(v) => map(v, |v| UserProperty::Uuid(*v)),
+    }
+}
+
+/// Converts a 16.16 fixed point number
+fn fixed_to_f64(fixed: &aseprite_loader::binary::scalars::Fixed) -> f64 {
+    (((fixed.0 as u32) << 16 | fixed.1 as u32) as i32) as f64 / 65536.0
+}
+
 #[derive(Debug)]
 pub enum AsepriteLoaderError {
     LoadSprite(aseprite_loader::loader::LoadSpriteError),
@@ -125,6 +1050,8 @@ pub enum AsepriteLoaderError {
     AtlasBuilder(bevy::image::TextureAtlasBuilderError),
     Io(std::io::Error),
     TextureAccess(bevy::image::TextureAccessError),
+    MissingTag(String),
+    InvalidFrameRange(RangeInclusive<u16>),
 }
 
 impl From<aseprite_loader::loader::LoadSpriteError> for AsepriteLoaderError {
@@ -164,3 +1091,211 @@ impl std::fmt::Display for AsepriteLoaderError {
 }
 
 impl std::error::Error for AsepriteLoaderError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_DATA: &[u8] = include_bytes!("../assets/tests/user_data.aseprite");
+    const TILEMAP: &[u8] = include_bytes!("../assets/tests/tilemap.aseprite");
+    const LAYER_GROUPS: &[u8] = include_bytes!("../assets/tests/layer_groups.aseprite");
+    const TILESET_USER_DATA: &[u8] = include_bytes!("../assets/tests/tileset_user_data.aseprite");
+
+    fn slice_keys(raw: &AsepriteFile, frame_range: RangeInclusive<usize>) -> Vec<(u32, i32, u32)> {
+        clip_slice_keys(&raw.slices()[0].slice_keys, &frame_range)
+            .iter()
+            .map(|key| (key.frame_number, key.x, key.width))
+            .collect()
+    }
+
+    #[test]
+    fn user_data_is_attached_to_its_chunk() {
+        let raw = parse_raw_file(USER_DATA).unwrap();
+        let user_data = FileUserData::parse(&raw, &(0..=2));
+
+        assert_eq!(user_data.sprite.text.as_deref(), Some("sprite text"));
+        assert_eq!(
+            user_data.sprite.color,
+            Some(Color::srgba_u8(10, 20, 30, 255))
+        );
+
+        let [body, hat] = &user_data.layers[..] else {
+            panic!("expected 2 layers, got {}", user_data.layers.len());
+        };
+        assert_eq!(body.text.as_deref(), Some("body text"));
+        assert_eq!(body.float("speed"), Some(-1.5));
+        assert_eq!(body.str("name"), Some("knight"));
+        // Only the properties map of the user, not the ones of extensions
+        assert!(body.property("ignored").is_none());
+        assert!(hat.is_empty());
+
+        // Empty user data is skipped, the following tag gets its own
+        assert!(!user_data.tags.contains_key(&AsepriteTag::from("idle")));
+        let hit = &user_data.tags[&AsepriteTag::from("hit")];
+        assert_eq!(hit.text.as_deref(), Some("sfx/hit.ogg"));
+
+        let hurtbox = &user_data.slices[&AsepriteSlice::from("hurtbox")];
+        assert_eq!(hurtbox.text.as_deref(), Some("damage=3"));
+
+        let cel_text = |frame, layer| {
+            user_data
+                .cels
+                .get(&(frame, layer))
+                .and_then(|data: &UserData| data.text.as_deref())
+        };
+        assert_eq!(cel_text(0, 0), Some("cel 0 body"));
+        assert_eq!(cel_text(0, 1), Some("cel 0 hat"));
+        assert_eq!(user_data.cels[&(0, 1)].int("damage"), Some(3));
+        assert_eq!(cel_text(1, 0), Some("cel 1 body"));
+        assert_eq!(cel_text(2, 0), None);
+    }
+
+    #[test]
+    fn tileset_user_data_isnt_the_sprites() {
+        let raw = parse_raw_file(TILESET_USER_DATA).unwrap();
+        let user_data = FileUserData::parse(&raw, &(0..=0));
+        assert!(user_data.sprite.is_empty());
+        assert_eq!(user_data.layers, [UserData::default()]);
+    }
+
+    #[test]
+    fn cel_user_data_follows_frame_range() {
+        let raw = parse_raw_file(USER_DATA).unwrap();
+        let user_data = FileUserData::parse(&raw, &(1..=2));
+
+        assert_eq!(user_data.cels.len(), 1);
+        assert_eq!(user_data.cels[&(0, 0)].text.as_deref(), Some("cel 1 body"));
+        // Other user data doesn't depend on frames
+        assert_eq!(user_data.sprite.text.as_deref(), Some("sprite text"));
+        assert!(user_data.tags.contains_key(&AsepriteTag::from("hit")));
+    }
+
+    #[test]
+    fn slice_keys_are_clipped_to_frame_range() {
+        let raw = load_file(USER_DATA).unwrap();
+
+        assert_eq!(slice_keys(&raw, 0..=2), [(0, 0, 4), (2, 1, 2)]);
+        assert_eq!(slice_keys(&raw, 0..=1), [(0, 0, 4)]);
+        // The key preceding the range moves to its first frame
+        assert_eq!(slice_keys(&raw, 1..=2), [(0, 0, 4), (1, 1, 2)]);
+        // A key on the first frame replaces the preceding ones
+        assert_eq!(slice_keys(&raw, 2..=2), [(0, 1, 2)]);
+    }
+
+    #[test]
+    fn identical_frames_share_atlas_index() {
+        let raw = load_file(USER_DATA).unwrap();
+
+        // The body cel of the last frame is linked to the previous one
+        assert_eq!(
+            unique_frames(&raw, &(0..=2), &[true, true]),
+            (vec![0, 1], vec![0, 1, 1])
+        );
+        // Without the body, only the first frame has a cel
+        assert_eq!(
+            unique_frames(&raw, &(0..=2), &[false, true]),
+            (vec![0, 1], vec![0, 1, 1])
+        );
+        assert_eq!(
+            unique_frames(&raw, &(1..=2), &[false, true]),
+            (vec![1], vec![0, 0])
+        );
+        assert_eq!(
+            unique_frames(&raw, &(0..=2), &[false, false]),
+            (vec![0], vec![0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn layers_sharing_a_name_are_split_by_path() {
+        let raw = load_file(LAYER_GROUPS).unwrap();
+        let layers = layers_info(&raw);
+        let mask = AsepriteLayers::All.mask(&layers);
+        let masks = AsepriteLayerSplit::Layers.masks(&layers, &mask);
+        let paths = masks
+            .iter()
+            .map(|(path, _)| path.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["front/hand", "back/hand"]);
+        assert_eq!(masks[1].1, [false, false, false, true]);
+
+        let info = AsepriteInfo {
+            layers,
+            ..default()
+        };
+        assert_eq!(info.layer_index("back/hand"), Some(3));
+        assert_eq!(info.layer_index("hand"), Some(1));
+        assert_eq!(info.layer_index("back"), Some(2));
+        assert_eq!(info.layer_index("front/back"), None);
+    }
+
+    #[test]
+    fn tilemap_layers_are_skipped() {
+        let raw = load_file(TILEMAP).unwrap();
+        let layers = layers_info(&raw);
+        let names = layers
+            .iter()
+            .map(|layer| layer.name.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["background", "top"]);
+
+        let cel_layers = |frame: usize| {
+            raw.frames()[frame]
+                .cels
+                .iter()
+                .map(|cel| cel.layer_index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cel_layers(0), [0, 1]);
+        assert_eq!(cel_layers(1), [1]);
+
+        // The top layer comes after the tilemap layer in the file
+        let mask = AsepriteLayers::Include(vec!["top".into()]).mask(&layers);
+        assert_eq!(mask, [false, true]);
+        let mut pixels = vec![0; 8 * 8 * 4];
+        raw.render_frame(1, &mut pixels, &LayerSelection::Mask(mask))
+            .unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..][..4];
+        assert_eq!(pixel(7, 7), [0, 0, 255, 255]);
+        assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn tilemaps_are_parsed() {
+        let raw_file = parse_raw_file(TILEMAP).unwrap();
+        let tiles = FileTiles::parse(&raw_file, None, &(0..=1)).unwrap();
+
+        let [(name, tile_size, layout, _)] = &tiles.tilesets[..] else {
+            panic!("expected 1 tileset, got {}", tiles.tilesets.len());
+        };
+        assert_eq!(name, "tiles");
+        assert_eq!(*tile_size, UVec2::new(2, 2));
+        assert_eq!(layout.len(), 3);
+
+        let [tilemap] = &tiles.tilemaps[..] else {
+            panic!("expected 1 tilemap, got {}", tiles.tilemaps.len());
+        };
+        assert_eq!(tilemap.layer, AsepriteLayer::from("ground"));
+        assert_eq!(tilemap.tileset, 0);
+        let grid = tilemap.frames[0].as_ref().unwrap();
+        assert_eq!(grid.offset, IVec2::new(2, 4));
+        assert_eq!(grid.size, UVec2::new(2, 2));
+        let tile = |index, flip_x, flip_diagonal| AsepriteTile {
+            index,
+            flip_x,
+            flip_y: false,
+            flip_diagonal,
+        };
+        assert_eq!(
+            grid.tiles,
+            [
+                tile(1, false, false),
+                tile(2, true, false),
+                tile(0, false, false),
+                tile(1, false, true)
+            ]
+        );
+        // The cel of the second frame is linked to the first one
+        assert_eq!(tilemap.frames[1].as_ref(), Some(grid));
+    }
+}
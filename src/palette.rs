@@ -0,0 +1,216 @@
+use bevy::log;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::info::AsepriteInfo;
+
+/// A recoloring of Aseprite atlases, mapping colors of the original palette to new colors
+///
+/// Atlases are stored in RGBA, so pixels are matched on their color and keep their alpha.
+/// Colors blended with other layers aren't matched.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct AsepriteRemap {
+    colors: Vec<([u8; 3], [u8; 4])>, // Sorted by original color
+}
+
+impl AsepriteRemap {
+    /// Maps each color of the sprite's palette to the color with the same index
+    ///
+    /// The transparent color is left as is. Sprites that aren't in indexed mode have no palette,
+    /// nothing gets remapped then. When the palette holds a color several times, its first
+    /// index decides the new color.
+    pub fn from_palette(info: &AsepriteInfo, colors: impl IntoIterator<Item = Color>) -> Self {
+        if info.palette.is_none() {
+            log::warn!("Aseprite file has no palette: no colors to remap");
+            return Self::default();
+        }
+        info.palette_colors()
+            .into_iter()
+            .zip(colors)
+            .enumerate()
+            .filter(|(index, _)| *index != info.transparent_palette as usize)
+            .fold(Self::default(), |remap, (_, (from, to))| {
+                if remap.find(from).is_ok() {
+                    remap
+                } else {
+                    remap.with_color(from, to)
+                }
+            })
+    }
+
+    /// Maps the sprite's palette to the colors of a GIMP palette (`.gpl`) file
+    ///
+    /// Returns `None` when the content isn't a GIMP palette.
+    pub fn from_gpl(info: &AsepriteInfo, gpl: &str) -> Option<Self> {
+        let mut lines = gpl.lines();
+        if lines.next()?.trim() != "GIMP Palette" {
+            return None;
+        }
+        let colors = lines
+            .map(str::trim)
+            .filter(|line| {
+                !line.is_empty()
+                    && !line.starts_with('#')
+                    && !line.starts_with("Name:")
+                    && !line.starts_with("Columns:")
+            })
+            .map(|line| {
+                let mut channels = line.split_whitespace().map(str::parse::<u8>);
+                match (channels.next(), channels.next(), channels.next()) {
+                    (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Some(Color::srgb_u8(r, g, b)),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self::from_palette(info, colors))
+    }
+
+    /// Maps a color to another one, the alpha of the original color is ignored
+    pub fn with_color(mut self, from: Color, to: Color) -> Self {
+        let [r, g, b, _] = from.to_srgba().to_u8_array();
+        let to = to.to_srgba().to_u8_array();
+        match self.find(from) {
+            Ok(index) => self.colors[index].1 = to,
+            Err(index) => self.colors.insert(index, ([r, g, b], to)),
+        }
+        self
+    }
+
+    /// The index of the color's mapping, or where to insert it
+    fn find(&self, color: Color) -> Result<usize, usize> {
+        let [r, g, b, _] = color.to_srgba().to_u8_array();
+        self.colors
+            .binary_search_by_key(&[r, g, b], |(from, _)| *from)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Recolors RGBA pixels
+    fn apply(&self, data: &mut [u8]) {
+        for pixel in data.chunks_exact_mut(4) {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let color = [pixel[0], pixel[1], pixel[2]];
+            if let Ok(index) = self.colors.binary_search_by_key(&color, |(from, _)| *from) {
+                let [r, g, b, a] = self.colors[index].1;
+                pixel.copy_from_slice(&[r, g, b, (pixel[3] as u16 * a as u16 / 255) as u8]);
+            }
+        }
+    }
+}
+
+/// A cache of recolored atlas images, shared by all the entities using the same remap
+///
+/// Recolored images share the atlas layout of the original one, they can be set as
+/// [`Sprite::image`] while keeping the sprite's [`TextureAtlas`]. The recolors of an atlas
+/// image are dropped when it is modified, for instance on hot reload, so they are generated
+/// again on next use.
+#[derive(Resource, Debug, Default)]
+pub struct AsepriteRecolors {
+    images: HashMap<(AssetId<Image>, AsepriteRemap), Handle<Image>>,
+}
+
+impl AsepriteRecolors {
+    /// The image recolored with the remap, generated on first use
+    ///
+    /// Returns `None` when the original image isn't loaded or its data isn't kept on the CPU.
+    pub fn recolor(
+        &mut self,
+        images: &mut Assets<Image>,
+        image: &Handle<Image>,
+        remap: &AsepriteRemap,
+    ) -> Option<Handle<Image>> {
+        let key = (image.id(), remap.clone());
+        if let Some(recolored) = self.images.get(&key) {
+            return Some(recolored.clone());
+        }
+        let mut recolored = images.get(image)?.clone();
+        remap.apply(recolored.data.as_mut()?);
+        let recolored = images.add(recolored);
+        self.images.insert(key, recolored.clone());
+        Some(recolored)
+    }
+
+    /// Drops the recolored images of an atlas image
+    pub fn remove(&mut self, image: impl Into<AssetId<Image>>) {
+        let image = image.into();
+        self.images.retain(|(id, _), _| *id != image);
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+    }
+}
+
+/// Drops the recolors of modified or removed atlas images
+pub fn invalidate_recolors(
+    mut events: MessageReader<AssetEvent<Image>>,
+    mut recolors: ResMut<AsepriteRecolors>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            recolors.remove(*id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aseprite_loader::binary::scalars::Color as PaletteColor;
+
+    use crate::info::Palette;
+
+    use super::*;
+
+    /// An indexed sprite with the given palette entries, the first one being transparent
+    fn info(entries: &[[u8; 4]]) -> AsepriteInfo {
+        let mut palette = Palette::default();
+        for (color, [red, green, blue, alpha]) in palette.colors.iter_mut().zip(entries) {
+            *color = PaletteColor {
+                red: *red,
+                green: *green,
+                blue: *blue,
+                alpha: *alpha,
+            };
+        }
+        AsepriteInfo {
+            palette: Some(palette),
+            palette_size: entries.len(),
+            ..default()
+        }
+    }
+
+    fn recolor(remap: &AsepriteRemap, pixel: [u8; 4]) -> [u8; 4] {
+        let mut data = pixel;
+        remap.apply(&mut data);
+        data
+    }
+
+    #[test]
+    fn palette_padding_is_ignored() {
+        let info = info(&[[0, 0, 0, 0], [0, 0, 0, 255], [255, 0, 0, 255]]);
+        assert_eq!(info.palette_colors().len(), 3);
+
+        let remap = AsepriteRemap::from_palette(
+            &info,
+            [Color::NONE, Color::WHITE, Color::srgb_u8(0, 0, 255)]
+                .into_iter()
+                .chain([Color::NONE; 253]),
+        );
+        assert_eq!(recolor(&remap, [0, 0, 0, 255]), [255, 255, 255, 255]);
+        assert_eq!(recolor(&remap, [255, 0, 0, 255]), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn first_palette_entry_wins() {
+        let info = info(&[[0, 0, 0, 0], [10, 20, 30, 255], [10, 20, 30, 255]]);
+        let remap = AsepriteRemap::from_palette(
+            &info,
+            [Color::NONE, Color::WHITE, Color::srgb_u8(255, 0, 0)],
+        );
+        assert_eq!(recolor(&remap, [10, 20, 30, 128]), [255, 255, 255, 128]);
+    }
+}
@@ -1,27 +1,156 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
 
-use crate::anim::{AsepriteAnimation, refresh_animations, update_animations};
+use crate::anim::{
+    AsepriteAnimation, AsepriteLayer, AsepriteSlice, refresh_anchors, refresh_animations,
+    refresh_layer_sprites, refresh_slice_attachments, reload_animations, setup_animations,
+    update_animations,
+};
+use crate::events::{
+    AsepriteFrameChanged, AsepriteTagFinished, AsepriteTagLooped, AsepriteTagStarted,
+};
 use crate::info::AsepriteInfo;
 use crate::loader::AsepriteLoader;
+use crate::palette::{AsepriteRecolors, invalidate_recolors};
+use crate::state_machine::update_state_machines;
+use crate::tilemap::{AsepriteTilemap, AsepriteTileset, spawn_tilemap_sprites};
+
+/// The plugin loading Aseprite files and playing their animations
+///
+/// By default animations advance in [`Update`] with the default [`Time`]. Both can be
+/// configured, for instance to animate deterministically in [`FixedUpdate`]:
+///
+/// ```rust
+/// # use bevy::prelude::*;
+/// # use bevy_mod_aseprite::AsepritePlugin;
+/// fn build(app: &mut App) {
+///     app.add_plugins(
+///         AsepritePlugin::default()
+///             .in_schedule(FixedUpdate)
+///             .with_time::<Fixed>(),
+///     );
+/// }
+/// ```
+pub struct AsepritePlugin<T = ()> {
+    schedule: InternedScheduleLabel,
+    _time: PhantomData<fn() -> T>,
+}
 
-pub struct AsepritePlugin;
+impl Default for AsepritePlugin {
+    fn default() -> Self {
+        Self {
+            schedule: Update.intern(),
+            _time: PhantomData,
+        }
+    }
+}
+
+impl<T> AsepritePlugin<T> {
+    /// Runs the animation systems in the given schedule
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
 
-impl Plugin for AsepritePlugin {
+    /// Advances animations with the given [`Time`] clock, such as [`Fixed`] or [`Real`]
+    pub fn with_time<U>(self) -> AsepritePlugin<U> {
+        AsepritePlugin {
+            schedule: self.schedule,
+            _time: PhantomData,
+        }
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> Plugin for AsepritePlugin<T> {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.init_asset::<AsepriteAsset>()
+            .init_asset::<AsepriteTilemap>()
             .register_asset_loader(AsepriteLoader)
-            .add_systems(Update, update_animations.in_set(AsepriteSystems::Animate))
-            .add_systems(Update, refresh_animations.in_set(AsepriteSystems::Refresh));
+            .init_resource::<AsepriteTimeScale>()
+            .init_resource::<AsepriteRecolors>()
+            .add_message::<AsepriteTagStarted>()
+            .add_message::<AsepriteFrameChanged>()
+            .add_message::<AsepriteTagLooped>()
+            .add_message::<AsepriteTagFinished>()
+            // Asset events of the previous frame, before user systems recolor again
+            .add_systems(First, invalidate_recolors)
+            .add_systems(
+                self.schedule,
+                update_state_machines
+                    .in_set(AsepriteSystems::StateMachine)
+                    .before(AsepriteSystems::Animate),
+            )
+            .add_systems(
+                self.schedule,
+                (
+                    reload_animations::<Aseprite>,
+                    setup_animations::<Aseprite>,
+                    update_animations::<T, Aseprite>,
+                )
+                    .chain()
+                    .in_set(AsepriteSystems::Animate),
+            )
+            .add_systems(
+                self.schedule,
+                (refresh_animations::<Aseprite>, spawn_tilemap_sprites)
+                    .in_set(AsepriteSystems::Refresh),
+            )
+            .add_systems(
+                self.schedule,
+                (
+                    refresh_anchors,
+                    refresh_layer_sprites,
+                    refresh_slice_attachments,
+                )
+                    .chain()
+                    .in_set(AsepriteSystems::Refresh)
+                    .after(AsepriteSystems::Animate),
+            );
+
+        #[cfg(feature = "bevy_ui")]
+        app.add_systems(
+            self.schedule,
+            (
+                reload_animations::<AsepriteUi>,
+                setup_animations::<AsepriteUi>,
+                update_animations::<T, AsepriteUi>,
+            )
+                .chain()
+                .in_set(AsepriteSystems::Animate),
+        )
+        .add_systems(
+            self.schedule,
+            refresh_animations::<AsepriteUi>.in_set(AsepriteSystems::Refresh),
+        );
     }
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AsepriteSystems {
+    /// Evaluates the transitions of [`AsepriteStateMachine`] components
+    ///
+    /// [`AsepriteStateMachine`]: crate::AsepriteStateMachine
+    StateMachine,
     Animate,
     Refresh,
 }
 
+/// A multiplier applied to the speed of all animations
+///
+/// Negative values play animations backwards.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Deref, DerefMut)]
+pub struct AsepriteTimeScale(pub f32);
+
+impl Default for AsepriteTimeScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 #[derive(Debug, TypePath, Asset)]
 pub struct AsepriteAsset {
     /// Info stores data such as tags and slices
@@ -32,6 +161,60 @@ pub struct AsepriteAsset {
 
     /// The actual atlas image
     pub atlas_texture: Handle<Image>,
+
+    /// Atlases of individual layers by path, see [`AsepriteLoaderSettings::split_layers`]
+    ///
+    /// Use [`AsepriteAsset::layer_atlas`] to find them by name as well.
+    ///
+    /// [`AsepriteLoaderSettings::split_layers`]: crate::AsepriteLoaderSettings::split_layers
+    pub layers: HashMap<AsepriteLayer, AsepriteLayerAtlas>,
+
+    /// Atlases of the tilesets, indexed by [`AsepriteTilemap::tileset`]
+    pub tilesets: Vec<AsepriteTileset>,
+
+    /// Tilemap layers
+    pub tilemaps: HashMap<AsepriteLayer, Handle<AsepriteTilemap>>,
+}
+
+impl AsepriteAsset {
+    /// The atlas of a layer found by path or by name, see [`AsepriteInfo::layer`]
+    pub fn layer_atlas(&self, layer: impl Into<AsepriteLayer>) -> Option<&AsepriteLayerAtlas> {
+        let layer = self.info.layer(layer)?;
+        self.layers.get(&self.info.layer_path(layer))
+    }
+
+    /// A sprite drawing a nine-patch slice at an absolute frame index, with its center and
+    /// sides scaled to the sprite's size
+    ///
+    /// Returns `None` when the slice has no key at this frame, or no center.
+    pub fn nine_patch_sprite(
+        &self,
+        slice: impl Into<AsepriteSlice>,
+        frame: usize,
+        size: Vec2,
+    ) -> Option<Sprite> {
+        let slice = slice.into();
+        let slicer = self.info.slice_texture_slicer(slice.clone(), frame)?;
+        let rect = self.info.slice_atlas_rect(slice, frame)?;
+        Some(Sprite {
+            image: self.atlas_texture.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: self.atlas_layout.clone(),
+                index: self.info.atlas_index(frame),
+            }),
+            rect: Some(rect),
+            custom_size: Some(size),
+            image_mode: SpriteImageMode::Sliced(slicer),
+            ..default()
+        })
+    }
+}
+
+/// The atlas of a single layer, sharing the atlas indices of the combined atlas
+#[derive(Debug, Clone)]
+pub struct AsepriteLayerAtlas {
+    pub atlas_layout: Handle<TextureAtlasLayout>,
+    pub atlas_texture: Handle<Image>,
 }
 
 /// A component for drawing sprites from an Aseprite animation
@@ -41,3 +224,88 @@ pub struct Aseprite {
     pub asset: Handle<AsepriteAsset>,
     pub anim: AsepriteAnimation,
 }
+
+/// A component for drawing an Aseprite animation in a UI [`ImageNode`]
+///
+/// The image and atlas of the node are set up from the asset when the node has no atlas.
+#[cfg(feature = "bevy_ui")]
+#[derive(Component, Default, Clone)]
+#[require(ImageNode)]
+pub struct AsepriteUi {
+    pub asset: Handle<AsepriteAsset>,
+    pub anim: AsepriteAnimation,
+}
+
+/// The anchor of an [`Aseprite`] sprite relative to its full canvas
+///
+/// Only used when frames are trimmed (see [`AsepriteLoaderSettings::trim`]) or when the
+/// [`AsepritePivot`] slice has no pivot, the sprite's [`Anchor`] is then computed for each
+/// frame so that the sprite doesn't jitter. Without this component the canvas is centered.
+///
+/// [`AsepriteLoaderSettings::trim`]: crate::AsepriteLoaderSettings::trim
+/// [`Anchor`]: bevy::sprite::Anchor
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Deref, DerefMut)]
+pub struct AsepriteAnchor(pub bevy::sprite::Anchor);
+
+/// A component anchoring an [`Aseprite`] sprite on the pivot of a slice
+///
+/// The sprite's [`Anchor`] is updated for each frame, so that characters drawn in frames of
+/// different shapes stay in place. Frames where the slice has no pivot fall back to the
+/// [`AsepriteAnchor`].
+///
+/// [`Anchor`]: bevy::sprite::Anchor
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct AsepritePivot {
+    pub slice: AsepriteSlice,
+}
+
+impl AsepritePivot {
+    pub fn new(slice: impl Into<AsepriteSlice>) -> Self {
+        Self {
+            slice: slice.into(),
+        }
+    }
+}
+
+/// A component for drawing a single layer of the parent [`Aseprite`] entity
+///
+/// It follows the parent's animation frame and flipping, the layer's atlas must have been
+/// built by the loader (see [`AsepriteLoaderSettings::split_layers`]). The layer is found by
+/// path or by name, see [`AsepriteInfo::layer`]. Layers can be toggled with the [`Visibility`]
+/// of the entity.
+///
+/// [`AsepriteLoaderSettings::split_layers`]: crate::AsepriteLoaderSettings::split_layers
+#[derive(Component, Default, Clone)]
+#[require(Sprite)]
+pub struct AsepriteLayerSprite {
+    pub layer: AsepriteLayer,
+}
+
+/// A component placing an entity on a slice of the parent [`Aseprite`] entity
+///
+/// The entity's translation is kept at the center of the slice for the current frame, honoring
+/// the parent's flipping and anchor. This suits hurtboxes or weapon sockets.
+#[derive(Component, Debug, Default, Clone)]
+#[require(Transform)]
+pub struct AsepriteSliceAttachment {
+    pub slice: AsepriteSlice,
+    /// The slice bounds in the parent's local space, `None` when the slice has no key yet
+    pub rect: Option<Rect>,
+}
+
+impl AsepriteSliceAttachment {
+    pub fn new(slice: impl Into<AsepriteSlice>) -> Self {
+        Self {
+            slice: slice.into(),
+            rect: None,
+        }
+    }
+}
+
+impl AsepriteLayerSprite {
+    pub fn new(layer: impl Into<AsepriteLayer>) -> Self {
+        Self {
+            layer: layer.into(),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use bevy::log;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::anim::AsepriteTag;
+use crate::plugin::{Aseprite, AsepriteAsset};
+
+/// A component switching the tags of an [`Aseprite`] animation based on parameters
+///
+/// States are the tags of the Aseprite file, the animation moves from one to another along
+/// the first transition whose conditions are all met. Transitions are evaluated in
+/// [`AsepriteSystems::StateMachine`], right before animations are updated.
+///
+/// ```rust
+/// # use bevy_mod_aseprite::{AsepriteCondition, AsepriteStateMachine};
+/// let state_machine = AsepriteStateMachine::default()
+///     .with_transition("stand", "move", [AsepriteCondition::Greater("speed".into(), 0.1)])
+///     .with_transition("move", "stand", [AsepriteCondition::Less("speed".into(), 0.1)])
+///     .with_any_transition("attack", [AsepriteCondition::Triggered("attack".into())])
+///     .with_transition("attack", "stand", [AsepriteCondition::Finished]);
+/// ```
+///
+/// [`AsepriteSystems::StateMachine`]: crate::AsepriteSystems::StateMachine
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AsepriteStateMachine {
+    pub transitions: Vec<AsepriteTransition>,
+    #[serde(skip)]
+    bools: HashMap<String, bool>,
+    #[serde(skip)]
+    floats: HashMap<String, f32>,
+    #[serde(skip)]
+    triggers: HashSet<String>,
+}
+
+/// A transition between two tags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AsepriteTransition {
+    /// The tag the transition starts from, `None` for any tag
+    pub from: Option<AsepriteTag>,
+    pub to: AsepriteTag,
+    /// Conditions that must all be met, an empty list always passes
+    pub conditions: Vec<AsepriteCondition>,
+}
+
+/// A condition of an [`AsepriteTransition`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AsepriteCondition {
+    /// The bool parameter is set to true
+    IsTrue(String),
+    /// The bool parameter is set to false or unset
+    IsFalse(String),
+    /// The float parameter is greater than the value
+    Greater(String, f32),
+    /// The float parameter is less than the value
+    Less(String, f32),
+    /// The trigger parameter is set, it gets reset when the transition happens
+    Triggered(String),
+    /// The current tag is finished, or completed a loop when it repeats forever
+    Finished,
+}
+
+impl AsepriteStateMachine {
+    /// Adds a transition from one tag to another
+    pub fn with_transition(
+        mut self,
+        from: impl Into<AsepriteTag>,
+        to: impl Into<AsepriteTag>,
+        conditions: impl IntoIterator<Item = AsepriteCondition>,
+    ) -> Self {
+        self.transitions.push(AsepriteTransition {
+            from: Some(from.into()),
+            to: to.into(),
+            conditions: conditions.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Adds a transition from any other tag
+    pub fn with_any_transition(
+        mut self,
+        to: impl Into<AsepriteTag>,
+        conditions: impl IntoIterator<Item = AsepriteCondition>,
+    ) -> Self {
+        self.transitions.push(AsepriteTransition {
+            from: None,
+            to: to.into(),
+            conditions: conditions.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Sets a bool parameter
+    pub fn set_bool(&mut self, param: impl Into<String>, value: bool) {
+        self.bools.insert(param.into(), value);
+    }
+
+    /// A bool parameter, false when unset
+    pub fn bool(&self, param: &str) -> bool {
+        self.bools.get(param).copied().unwrap_or(false)
+    }
+
+    /// Sets a float parameter
+    pub fn set_float(&mut self, param: impl Into<String>, value: f32) {
+        self.floats.insert(param.into(), value);
+    }
+
+    /// A float parameter, 0 when unset
+    pub fn float(&self, param: &str) -> f32 {
+        self.floats.get(param).copied().unwrap_or(0.0)
+    }
+
+    /// Sets a trigger parameter, it stays set until a transition uses it
+    pub fn set_trigger(&mut self, param: impl Into<String>) {
+        self.triggers.insert(param.into());
+    }
+
+    /// Resets a trigger parameter
+    pub fn reset_trigger(&mut self, param: &str) {
+        self.triggers.remove(param);
+    }
+
+    /// Returns whether a trigger parameter is set
+    pub fn is_triggered(&self, param: &str) -> bool {
+        self.triggers.contains(param)
+    }
+
+    /// Finds the first transition available from the current tag
+    fn next_transition(&self, ase: &Aseprite) -> Option<&AsepriteTransition> {
+        let current = ase.anim.tag();
+        self.transitions.iter().find(|transition| {
+            let from_current = match &transition.from {
+                Some(from) => Some(from) == current,
+                None => Some(&transition.to) != current,
+            };
+            from_current
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| match condition {
+                        AsepriteCondition::IsTrue(param) => self.bool(param),
+                        AsepriteCondition::IsFalse(param) => !self.bool(param),
+                        AsepriteCondition::Greater(param, value) => self.float(param) > *value,
+                        AsepriteCondition::Less(param, value) => self.float(param) < *value,
+                        AsepriteCondition::Triggered(param) => self.is_triggered(param),
+                        AsepriteCondition::Finished => {
+                            ase.anim.is_finished() || ase.anim.loops_completed() > 0
+                        }
+                    })
+        })
+    }
+}
+
+pub fn update_state_machines(
+    aseprites: Res<Assets<AsepriteAsset>>,
+    mut aseprites_query: Query<(Entity, &mut AsepriteStateMachine, &mut Aseprite)>,
+    mut removed: RemovedComponents<AsepriteStateMachine>,
+    mut logged: Local<HashSet<(Entity, AsepriteTag)>>,
+) {
+    for entity in removed.read() {
+        logged.retain(|(logged_entity, _)| *logged_entity != entity);
+    }
+
+    for (entity, mut state_machine, mut ase) in aseprites_query.iter_mut() {
+        let Some(ase_asset) = aseprites.get(&ase.asset) else {
+            continue;
+        };
+        let Some(transition) = state_machine.next_transition(&ase).cloned() else {
+            continue;
+        };
+        if !ase_asset.info.tags.contains_key(&transition.to) {
+            // The transition stays available, it is reported once
+            if logged.insert((entity, transition.to.clone())) {
+                log::error!("Tag {:?} wasn't found.", transition.to);
+            }
+            continue;
+        }
+
+        for condition in transition.conditions.iter() {
+            if let AsepriteCondition::Triggered(param) = condition {
+                state_machine.reset_trigger(param);
+            }
+        }
+        ase.anim.play_tag(&ase_asset.info, transition.to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::anim::AsepriteAnimation;
+    use crate::anim::tests::{frames, info};
+    use crate::info::{AnimationDirection, AsepriteInfo};
+
+    use super::*;
+
+    fn tags_info() -> AsepriteInfo {
+        info(
+            8,
+            &[
+                ("stand", 0..=1, AnimationDirection::Forward, None),
+                ("move", 2..=3, AnimationDirection::Forward, None),
+                ("attack", 4..=5, AnimationDirection::Forward, Some(1)),
+                ("jump", 6..=7, AnimationDirection::Forward, None),
+            ],
+        )
+    }
+
+    fn aseprite(info: &AsepriteInfo, tag: &str) -> Aseprite {
+        Aseprite {
+            anim: AsepriteAnimation::new(info, tag),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn first_available_transition_is_taken() {
+        let info = tags_info();
+        let ase = aseprite(&info, "stand");
+        let state_machine = AsepriteStateMachine::default()
+            .with_transition("move", "jump", [])
+            .with_transition(
+                "stand",
+                "move",
+                [AsepriteCondition::IsTrue("moving".into())],
+            )
+            .with_transition("stand", "jump", [])
+            .with_transition("stand", "attack", []);
+        let transition = state_machine.next_transition(&ase).unwrap();
+        assert_eq!(transition.to, "jump".into());
+
+        // Transitions from any tag skip the current one
+        let state_machine = AsepriteStateMachine::default()
+            .with_any_transition("stand", [])
+            .with_any_transition("move", []);
+        let transition = state_machine.next_transition(&ase).unwrap();
+        assert_eq!(transition.to, "move".into());
+    }
+
+    #[test]
+    fn trigger_is_reset_by_its_transition() {
+        let info = tags_info();
+        let anim = AsepriteAnimation::new(&info, "stand");
+        let mut assets = Assets::<AsepriteAsset>::default();
+        let asset = assets.add(AsepriteAsset {
+            info,
+            atlas_layout: default(),
+            atlas_texture: default(),
+            layers: default(),
+            tilesets: default(),
+            tilemaps: default(),
+        });
+        let mut world = World::new();
+        world.insert_resource(assets);
+
+        let mut state_machine = AsepriteStateMachine::default()
+            .with_transition(
+                "move",
+                "jump",
+                [AsepriteCondition::Triggered("jump".into())],
+            )
+            .with_any_transition("attack", [AsepriteCondition::Triggered("attack".into())]);
+        state_machine.set_trigger("jump");
+        state_machine.set_trigger("attack");
+        let entity = world.spawn((Aseprite { asset, anim }, state_machine)).id();
+
+        world.run_system_once(update_state_machines).unwrap();
+        let entity = world.entity(entity);
+        assert_eq!(
+            entity.get::<Aseprite>().unwrap().anim.tag(),
+            Some(&"attack".into())
+        );
+        let state_machine = entity.get::<AsepriteStateMachine>().unwrap();
+        assert!(!state_machine.is_triggered("attack"));
+        // The trigger of the transition that wasn't taken is kept
+        assert!(state_machine.is_triggered("jump"));
+    }
+
+    #[test]
+    fn finished_waits_for_a_loop() {
+        let info = tags_info();
+        let state_machine = AsepriteStateMachine::default()
+            .with_transition("stand", "move", [AsepriteCondition::Finished])
+            .with_transition("attack", "stand", [AsepriteCondition::Finished]);
+
+        // Endless tags are finished once they complete a loop
+        let mut ase = aseprite(&info, "stand");
+        assert_eq!(frames(&mut ase.anim, &info, 1), [1]);
+        assert!(state_machine.next_transition(&ase).is_none());
+        assert_eq!(frames(&mut ase.anim, &info, 1), [0]);
+        let transition = state_machine.next_transition(&ase).unwrap();
+        assert_eq!(transition.to, "move".into());
+
+        let mut ase = aseprite(&info, "attack");
+        assert_eq!(frames(&mut ase.anim, &info, 1), [5]);
+        assert!(state_machine.next_transition(&ase).is_none());
+        assert_eq!(frames(&mut ase.anim, &info, 1), [5]);
+        assert!(ase.anim.is_finished());
+        let transition = state_machine.next_transition(&ase).unwrap();
+        assert_eq!(transition.to, "stand".into());
+    }
+}
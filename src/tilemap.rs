@@ -0,0 +1,203 @@
+//! Tilesets and tilemap layers of Aseprite files.
+//!
+//! Each tileset is loaded as an atlas in [`AsepriteAsset::tilesets`], and each tilemap layer as
+//! an [`AsepriteTilemap`] in [`AsepriteAsset::tilemaps`]. The [`AsepriteTilemapSprites`]
+//! component spawns a sprite per tile of a tilemap layer.
+//!
+//! [`AsepriteAsset::tilesets`]: crate::AsepriteAsset::tilesets
+//! [`AsepriteAsset::tilemaps`]: crate::AsepriteAsset::tilemaps
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::log;
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+
+use crate::anim::AsepriteLayer;
+use crate::plugin::AsepriteAsset;
+
+/// A tile of a tilemap layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AsepriteTile {
+    /// Index of the tile in its tileset atlas, 0 is the empty tile
+    pub index: u32,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Whether the tile is transposed, before being flipped
+    pub flip_diagonal: bool,
+}
+
+impl AsepriteTile {
+    pub fn is_empty(&self) -> bool {
+        self.index == 0
+    }
+
+    /// The sprite flipping and rotation drawing this tile
+    pub fn sprite_transform(&self) -> (bool, bool, Quat) {
+        if self.flip_diagonal {
+            // A transposition is a flip on x followed by a quarter turn
+            (!self.flip_y, self.flip_x, Quat::from_rotation_z(FRAC_PI_2))
+        } else {
+            (self.flip_x, self.flip_y, Quat::IDENTITY)
+        }
+    }
+}
+
+/// The tiles of a tilemap layer at a given frame
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsepriteTileGrid {
+    /// Position in pixels from the top left corner of the canvas
+    pub offset: IVec2,
+    /// Size in number of tiles
+    pub size: UVec2,
+    /// Tiles row by row, from top to bottom
+    pub tiles: Vec<AsepriteTile>,
+}
+
+impl AsepriteTileGrid {
+    pub fn tile(&self, x: u32, y: u32) -> Option<&AsepriteTile> {
+        if x >= self.size.x || y >= self.size.y {
+            return None;
+        }
+        self.tiles.get((y * self.size.x + x) as usize)
+    }
+}
+
+/// A tilemap layer, loaded as the `tilemaps/{layer}` sub-asset
+#[derive(Debug, Clone, TypePath, Asset)]
+pub struct AsepriteTilemap {
+    pub layer: AsepriteLayer,
+    /// Index of the tileset in [`AsepriteAsset::tilesets`]
+    pub tileset: usize,
+    pub tile_size: UVec2,
+    /// The tiles of each loaded frame, `None` when the layer has no cel
+    pub frames: Vec<Option<AsepriteTileGrid>>,
+}
+
+/// A tileset, loaded as the `tilesets/{index}/atlas_layout` and
+/// `tilesets/{index}/atlas_texture` sub-assets
+///
+/// Atlas indices are the indices of [`AsepriteTile`].
+#[derive(Debug, Clone)]
+pub struct AsepriteTileset {
+    pub name: String,
+    pub tile_size: UVec2,
+    pub atlas_layout: Handle<TextureAtlasLayout>,
+    pub atlas_texture: Handle<Image>,
+}
+
+/// A component spawning a sprite per tile of a tilemap layer as children
+///
+/// Tiles are placed relative to the center of the canvas, like an [`Aseprite`][crate::Aseprite]
+/// sprite with the default anchor. Sprites are spawned again when the component changes or when
+/// the asset is reloaded.
+#[derive(Component, Debug, Default, Clone)]
+#[require(Transform, Visibility)]
+pub struct AsepriteTilemapSprites {
+    pub asset: Handle<AsepriteAsset>,
+    pub layer: AsepriteLayer,
+    /// The absolute frame index of the tiles
+    pub frame: usize,
+    tiles: Option<Vec<Entity>>,
+}
+
+impl AsepriteTilemapSprites {
+    pub fn new(asset: Handle<AsepriteAsset>, layer: impl Into<AsepriteLayer>) -> Self {
+        Self {
+            asset,
+            layer: layer.into(),
+            frame: 0,
+            tiles: None,
+        }
+    }
+
+    pub fn with_frame(mut self, frame: usize) -> Self {
+        self.frame = frame;
+        self
+    }
+}
+
+pub fn spawn_tilemap_sprites(
+    mut commands: Commands,
+    aseprites: Res<Assets<AsepriteAsset>>,
+    tilemaps: Res<Assets<AsepriteTilemap>>,
+    mut asset_events: MessageReader<AssetEvent<AsepriteAsset>>,
+    mut tilemaps_query: Query<(Entity, &mut AsepriteTilemapSprites)>,
+) {
+    let modified = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for (entity, mut tilemap_sprites) in tilemaps_query.iter_mut() {
+        if !tilemap_sprites.is_changed()
+            && tilemap_sprites.tiles.is_some()
+            && !modified.contains(&tilemap_sprites.asset.id())
+        {
+            continue;
+        }
+        let Some(ase_asset) = aseprites.get(&tilemap_sprites.asset) else {
+            continue;
+        };
+        let Some(tilemap) = ase_asset
+            .tilemaps
+            .get(&tilemap_sprites.layer)
+            .and_then(|handle| tilemaps.get(handle))
+        else {
+            log::error!(
+                "Aseprite handle {:?}: layer {:?} is not a tilemap",
+                tilemap_sprites.asset,
+                tilemap_sprites.layer
+            );
+            tilemap_sprites.bypass_change_detection().tiles = Some(Vec::new());
+            continue;
+        };
+        let tileset = &ase_asset.tilesets[tilemap.tileset];
+
+        let tilemap_sprites = tilemap_sprites.bypass_change_detection();
+        for tile in tilemap_sprites.tiles.take().unwrap_or_default() {
+            if let Ok(mut tile) = commands.get_entity(tile) {
+                tile.despawn();
+            }
+        }
+
+        let mut tiles = Vec::new();
+        if let Some(Some(grid)) = tilemap.frames.get(tilemap_sprites.frame) {
+            let canvas = UVec2::new(
+                ase_asset.info.dimensions.0 as u32,
+                ase_asset.info.dimensions.1 as u32,
+            )
+            .as_vec2();
+            let tile_size = tilemap.tile_size.as_vec2();
+            for y in 0..grid.size.y {
+                for x in 0..grid.size.x {
+                    let Some(tile) = grid.tile(x, y).filter(|tile| !tile.is_empty()) else {
+                        continue;
+                    };
+                    let (flip_x, flip_y, rotation) = tile.sprite_transform();
+                    let center =
+                        grid.offset.as_vec2() + (UVec2::new(x, y).as_vec2() + 0.5) * tile_size;
+                    let translation = Vec2::new(center.x - canvas.x / 2., canvas.y / 2. - center.y);
+                    let sprite = Sprite {
+                        image: tileset.atlas_texture.clone(),
+                        texture_atlas: Some(TextureAtlas {
+                            layout: tileset.atlas_layout.clone(),
+                            index: tile.index as usize,
+                        }),
+                        flip_x,
+                        flip_y,
+                        ..default()
+                    };
+                    let transform =
+                        Transform::from_translation(translation.extend(0.)).with_rotation(rotation);
+                    tiles.push(commands.spawn((sprite, transform, ChildOf(entity))).id());
+                }
+            }
+        }
+        tilemap_sprites.tiles = Some(tiles);
+    }
+}